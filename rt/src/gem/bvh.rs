@@ -0,0 +1,126 @@
+use super::mat::Mat;
+
+/// maximum number of items in the leaf of the tree
+const LEAF_SIZE: usize = 2;
+
+/// bounding volume hierarchy.
+///
+/// The tree of the boxes(oriented along axes) around the items, to find
+/// the items which can be hit by the ray, without checking all of them.
+/// The items are referenced by their indices in the list used to build the tree.
+pub struct Bvh {
+  nodes: Vec<Node>,
+  /// item indices, ordered so each leaf refers to the continuous range of them
+  items: Vec<usize>,
+  /// item indices without the box(f.e. infinite plane), they are always returned
+  unbounded: Vec<usize>,
+}
+
+enum Node {
+  Leaf { min: [f64; 3], max: [f64; 3], start: usize, end: usize },
+  Branch { min: [f64; 3], max: [f64; 3], left: usize, right: usize },
+}
+
+impl Bvh {
+  /// build the tree from the boxes of the items.
+  ///
+  /// Each box is \[min corner, max corner\], or None if the item can not be bounded.
+  /// The index of the box in the list is the index of the item
+  pub fn new(bounds: &[Option<[[f64; 3]; 2]>]) -> Bvh {
+    let mut bvh = Bvh { nodes: Vec::new(), items: Vec::new(), unbounded: Vec::new() };
+    for (index, b) in bounds.iter().enumerate() {
+      if b.is_some() { bvh.items.push(index) } else { bvh.unbounded.push(index) }
+    }
+    // the unbounded items are never used in the tree, so any box can be placed for them
+    let boxes: Vec<[[f64; 3]; 2]> = bounds.iter().map(|b| b.unwrap_or([[0.0; 3]; 2])).collect();
+
+    if !bvh.items.is_empty() { bvh.build(&boxes, 0, bvh.items.len()); }
+    bvh
+  }
+
+  /// build the node for the items\[start..end\], and return its index.
+  ///
+  /// The items are split by the middle, along the longest side of the box around their centers
+  fn build(&mut self, bounds: &[[[f64; 3]; 2]], start: usize, end: usize) -> usize {
+    let mut min = [f64::MAX; 3];
+    let mut max = [f64::MIN; 3];
+    let mut center_min = [f64::MAX; 3];
+    let mut center_max = [f64::MIN; 3];
+    for item in &self.items[start..end] {
+      let [b_min, b_max] = bounds[*item];
+      for axis in 0..3 {
+        min[axis] = min[axis].min(b_min[axis]);
+        max[axis] = max[axis].max(b_max[axis]);
+        let center = (b_min[axis] + b_max[axis]) * 0.5;
+        center_min[axis] = center_min[axis].min(center);
+        center_max[axis] = center_max[axis].max(center);
+      }
+    }
+
+    let index = self.nodes.len();
+    if end - start <= LEAF_SIZE {
+      self.nodes.push(Node::Leaf { min, max, start, end });
+      return index
+    }
+
+    // the longest side of the box around the centers
+    let axis = (0..3)
+      .max_by(|a, b| (center_max[*a] - center_min[*a]).total_cmp(&(center_max[*b] - center_min[*b])))
+      .unwrap_or(0);
+    self.items[start..end].sort_by(|a, b| {
+      (bounds[*a][0][axis] + bounds[*a][1][axis]).total_cmp(&(bounds[*b][0][axis] + bounds[*b][1][axis]))
+    });
+
+    // the place for the branch, the children are added after it
+    self.nodes.push(Node::Leaf { min, max, start, end });
+    let middle = (start + end) / 2;
+    let left = self.build(bounds, start, middle);
+    let right = self.build(bounds, middle, end);
+    self.nodes[index] = Node::Branch { min, max, left, right };
+    index
+  }
+
+  /// indices of the items, which boxes are hit by the ray(forward direction),
+  /// plus all the items without the box.
+  pub fn candidates(&self, ray: &Mat) -> Vec<usize> {
+    let mut found = self.unbounded.clone();
+    if self.nodes.is_empty() { return found }
+
+    let mut stack = vec![0];
+    while let Some(index) = stack.pop() {
+      match self.nodes[index] {
+        Node::Leaf { min, max, start, end } => {
+          if Bvh::ray_x_bounds(ray, &min, &max) { found.extend(&self.items[start..end]); }
+        }
+        Node::Branch { min, max, left, right } => {
+          if Bvh::ray_x_bounds(ray, &min, &max) { stack.push(right); stack.push(left); }
+        }
+      }
+    }
+    found
+  }
+
+  /// check the ray(forward direction) hits the box from min to max corners.
+  ///
+  /// The same slabs idea as Gem::ray_x_box, but without the hit point calculation
+  fn ray_x_bounds(ray: &Mat, min: &[f64; 3], max: &[f64; 3]) -> bool {
+    let o = ray.origin.to_array();
+    let v = ray.normal.to_array();
+
+    let mut t_enter = 0.0_f64;
+    let mut t_exit = f64::MAX;
+    for axis in 0..3 {
+      if v[axis] == 0.0 {
+        // the ray is parallel to the slab, so it must start between the faces
+        if o[axis] < min[axis] || o[axis] > max[axis] { return false }
+        continue;
+      }
+      let t1 = (min[axis] - o[axis]) / v[axis];
+      let t2 = (max[axis] - o[axis]) / v[axis];
+      t_enter = t_enter.max(t1.min(t2));
+      t_exit = t_exit.min(t1.max(t2));
+      if t_enter > t_exit { return false }
+    }
+    true
+  }
+}
@@ -0,0 +1,118 @@
+use super::dot::Dot;
+use super::mat::Mat;
+use super::spear::Spear;
+use super::utils::F64xyz;
+
+/// distance to move the start of the secondary ray from the surface, along the normal vector,
+/// so the ray does not hit the same surface in the start because of the calculation errors
+const RAY_OFFSET: f64 = 1e-4;
+
+/// the intersection of the ray and the surface of the object.
+///
+/// The ray intersection functions return Option\<Hit\>, None if there is no intersection
+#[derive(Debug, Clone, Copy)]
+pub struct Hit {
+  /// the ray parameter. The distance from the ray origin along the ray vector
+  pub t: f64,
+  /// the hit position
+  pub dot: Dot,
+  /// unit normal vector of the surface in the hit position,
+  /// directed against the ray (to the side of the ray origin)
+  pub normal: Spear,
+  /// the ray hits the front(outer) side of the surface.
+  /// For the flat surfaces, the front side is the side of the surface normal vector
+  pub front: bool,
+  /// index of the hit object, in the list of the objects good to trace
+  pub index: usize,
+}
+
+impl Hit {
+  /// the hit on the ray, with the ray parameter t.
+  ///
+  /// outward_normal is the surface normal vector directed outside of the object.
+  /// The index is zero, use with_index() to set the hit object index
+  pub fn new(ray: &Mat, t: f64, outward_normal: Spear) -> Hit {
+    let dot = Dot::new(
+      ray.origin.x + t * ray.normal.x,
+      ray.origin.y + t * ray.normal.y,
+      ray.origin.z + t * ray.normal.z,
+    );
+    let outward_normal = outward_normal.unit();
+    let front = outward_normal.scalar(&ray.normal) <= 0.0;
+    let normal = if front { outward_normal } else { outward_normal.back() };
+    Hit { t, dot, normal, front, index: 0 }
+  }
+
+  /// the same hit, of the object with index
+  pub fn with_index(&self, index: usize) -> Hit {
+    Hit { index, ..*self }
+  }
+
+  /// check the hit is closer to the ray origin, than the other one (or there is no other one)
+  pub fn is_closer(&self, other: &Option<Hit>) -> bool {
+    match other {
+      Some(other) => self.t < other.t,
+      None => true,
+    }
+  }
+
+  /// the first hit in the forward direction of the ray, from the near and the far hits of the object.
+  ///
+  /// The far hit is used if the ray origin is inside the object
+  pub fn forward(both: Option<[Hit; 2]>) -> Option<Hit> {
+    let [near, far] = both?;
+    if near.t >= 0.0 { Some(near) }
+    else if far.t >= 0.0 { Some(far) }
+    else { None }
+  }
+
+  /// the nearest hit from both, or None if there is no hit at all
+  pub fn nearest(a: Option<Hit>, b: Option<Hit>) -> Option<Hit> {
+    match (a, b) {
+      (Some(a), Some(b)) => if b.t < a.t { Some(b) } else { Some(a) },
+      (a, None) => a,
+      (None, b) => b,
+    }
+  }
+
+  /// the mirror reflection of the ray in the hit position.
+  ///
+  /// The reflected ray starts a bit above the surface, on the side of the ray origin
+  pub fn reflected(&self, ray: &Mat) -> Mat {
+    let d = ray.normal;
+    let n = self.normal;
+    let dn = 2.0 * d.scalar(&n);
+    Mat::new(
+      self.dot.offset(&n, RAY_OFFSET),
+      Spear::new(d.x - dn * n.x, d.y - dn * n.y, d.z - dn * n.z),
+    )
+  }
+
+  /// the refraction of the ray in the hit position(Snell's law), and the part of the light
+  /// reflected by the surface(Fresnel reflectance, Schlick approximation).
+  ///
+  /// The eta is the ratio of the refraction indices, from the side of the ray origin to the other side.
+  /// The refracted ray starts a bit under the surface, on the other side from the ray origin.
+  /// None if there is total internal reflection, so all the light is reflected
+  pub fn refracted(&self, ray: &Mat, eta: f64) -> Option<(Mat, f64)> {
+    let d = ray.normal;
+    let n = self.normal;
+    // the normal is directed against the ray, so the cos is positive
+    let cos_i = (-d.scalar(&n)).cut();
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+    if sin2_t > 1.0 { return None }
+    let cos_t = (1.0 - sin2_t).sqrt();
+
+    let k = eta * cos_i - cos_t;
+    let refracted = Mat::new(
+      self.dot.offset(&n, -RAY_OFFSET),
+      Spear::new(eta * d.x + k * n.x, eta * d.y + k * n.y, eta * d.z + k * n.z),
+    );
+
+    // the bigger angle(in the less dense side) is used
+    let r0 = ((1.0 - eta) / (1.0 + eta)).powi(2);
+    let cos = if eta > 1.0 { cos_t } else { cos_i };
+    let fresnel = r0 + (1.0 - r0) * (1.0 - cos).powi(5);
+    Some((refracted, fresnel))
+  }
+}
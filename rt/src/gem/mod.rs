@@ -1,5 +1,8 @@
 pub mod gem;
 pub mod dot;
 pub mod utils;
+pub mod roots;
+pub mod bvh;
+pub mod hit;
 pub mod spear;
 pub mod mat;
\ No newline at end of file
@@ -0,0 +1,203 @@
+use super::gem::Gem;
+use super::utils::F64xyz;
+
+impl Gem {
+
+  /// real roots of the quadratic equation a*x^2 + b*x + c = 0.
+  ///
+  /// If a is zero, then the linear equation is solved.
+  /// The roots are sorted from the smallest to the biggest
+  pub fn quadratic_roots(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a == 0.0 {
+      if b == 0.0 { return vec![] }
+      return vec![-c / b]
+    }
+
+    let d = b * b - 4.0 * a * c;
+    if d < 0.0 { return vec![] }
+
+    // the form without subtraction of close values, to keep precision
+    let q = -0.5 * (b + b.signum() * d.sqrt());
+    let mut roots = if q == 0.0 { vec![0.0] } else { vec![q / a, c / q] };
+    roots.sort_by(|x, y| x.total_cmp(y));
+    roots
+  }
+
+  /// real roots of the cubic equation a*x^3 + b*x^2 + c*x + d = 0.
+  ///
+  /// If a is zero, then the quadratic equation is solved, if a is tiny, see far_root_and.
+  /// The roots are sorted from the smallest to the biggest
+  pub fn cubic_roots(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    if a == 0.0 { return Gem::quadratic_roots(b, c, d) }
+    if a.abs() < 1e-9 * b.abs() { return Gem::far_root_and(Gem::quadratic_roots(b, c, d), a, b, c) }
+
+    // x^3 + b*x^2 + c*x + d = 0
+    let (b, c, d) = (b / a, c / a, d / a);
+
+    // depressed cubic y^3 + 3*q*y - 2*r = 0, where x = y - b/3
+    let q = (3.0 * c - b * b) / 9.0;
+    let r = (9.0 * b * c - 27.0 * d - 2.0 * b * b * b) / 54.0;
+    let shift = -b / 3.0;
+    let disc = q * q * q + r * r;
+
+    let mut roots = if disc > 0.0 {
+      // one real root (Cardano)
+      let s = (r + disc.sqrt()).cbrt();
+      let t = (r - disc.sqrt()).cbrt();
+      vec![shift + s + t]
+    } else if q == 0.0 {
+      // triple root
+      vec![shift]
+    } else {
+      // three real roots (trigonometric form), some of them can be equal
+      let theta = (r / (-q * q * q).sqrt()).cut().acos();
+      let m = 2.0 * (-q).sqrt();
+      (0..3)
+        .map(|k| shift + m * ((theta + 2.0 * std::f64::consts::PI * k as f64) / 3.0).cos())
+        .collect()
+    };
+    roots.sort_by(|x, y| x.total_cmp(y));
+    roots
+  }
+
+  /// real roots of the quartic equation a*x^4 + b*x^3 + c*x^2 + d*x + e = 0.
+  ///
+  /// If a is zero, then the cubic equation is solved, if a is tiny, see far_root_and.
+  /// Ferrari method, with the resolvent cubic. Each root is additionally
+  /// polished by Newton iterations on the original equation, because the
+  /// method loses precision for the big coefficients.
+  /// The roots are sorted from the smallest to the biggest
+  pub fn quartic_roots(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    if a == 0.0 { return Gem::cubic_roots(b, c, d, e) }
+    if a.abs() < 1e-9 * b.abs() { return Gem::far_root_and(Gem::cubic_roots(b, c, d, e), a, b, c) }
+
+    // x^4 + b*x^3 + c*x^2 + d*x + e = 0
+    let (b, c, d, e) = (b / a, c / a, d / a, e / a);
+
+    // depressed quartic y^4 + p*y^2 + q*y + r = 0, where x = y - b/4
+    let p = c - 3.0 * b * b / 8.0;
+    let q = d - b * c / 2.0 + b * b * b / 8.0;
+    let r = e - b * d / 4.0 + b * b * c / 16.0 - 3.0 * b * b * b * b / 256.0;
+    let shift = -b / 4.0;
+
+    let mut ys = Vec::new();
+    if q.abs() < 1e-12 * (1.0 + p.abs() + r.abs()) {
+      // biquadratic equation z^2 + p*z + r = 0, where z = y^2
+      for z in Gem::quadratic_roots(1.0, p, r) {
+        if z >= 0.0 { ys.push(z.sqrt()); ys.push(-z.sqrt()); }
+      }
+    } else {
+      // resolvent cubic 8*m^3 + 8*p*m^2 + (2*p^2 - 8*r)*m - q^2 = 0 always has positive root
+      let m = Gem::cubic_roots(8.0, 8.0 * p, 2.0 * p * p - 8.0 * r, -q * q)
+        .into_iter()
+        .fold(0.0_f64, f64::max);
+      if m <= 0.0 { return vec![] }
+
+      // split to two quadratic equations
+      let s = (2.0 * m).sqrt();
+      ys.extend(Gem::quadratic_roots(1.0, -s, p / 2.0 + m + q / (2.0 * s)));
+      ys.extend(Gem::quadratic_roots(1.0, s, p / 2.0 + m - q / (2.0 * s)));
+    }
+
+    let f = |x: f64| (((x + b) * x + c) * x + d) * x + e;
+    let df = |x: f64| ((4.0 * x + 3.0 * b) * x + 2.0 * c) * x + d;
+
+    let mut roots: Vec<f64> = ys
+      .into_iter()
+      .map(|y| {
+        let mut x = y + shift;
+        for _ in 0..2 {
+          let slope = df(x);
+          if slope == 0.0 { break }
+          x -= f(x) / slope;
+        }
+        x
+      })
+      .collect();
+    roots.sort_by(|x, y| x.total_cmp(y));
+    roots
+  }
+
+  /// the roots of the equation with the tiny leading coefficient a, comparing to the next one b.
+  ///
+  /// The division by a loses the precision of the near roots, so they are the roots of the
+  /// equation without the leading term, and the far root is added, because the sum of all
+  /// roots is -b/a, and the sum of the near ones(even not real) is -c/b
+  fn far_root_and(near_roots: Vec<f64>, a: f64, b: f64, c: f64) -> Vec<f64> {
+    let mut roots = near_roots;
+    roots.push(-b / a + c / b);
+    roots.sort_by(|x, y| x.total_cmp(y));
+    roots
+  }
+
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// the found roots are the expected ones, in the same order
+  fn assert_roots(found: Vec<f64>, expected: &[f64]) {
+    assert_eq!(found.len(), expected.len(), "roots {:?}, expected {:?}", found, expected);
+    for (x, e) in found.iter().zip(expected) {
+      assert!((x - e).abs() < 1e-6, "roots {:?}, expected {:?}", found, expected);
+    }
+  }
+
+  /// the roots without the repeated ones, the double root can be found twice or once
+  fn distinct(roots: Vec<f64>) -> Vec<f64> {
+    let mut distinct: Vec<f64> = Vec::new();
+    for x in roots {
+      if distinct.last().is_none_or(|last| (x - last).abs() > 1e-6) { distinct.push(x) }
+    }
+    distinct
+  }
+
+  #[test]
+  fn cubic_three_real_roots() {
+    // (x + 2)(x - 1)(x - 3)
+    assert_roots(Gem::cubic_roots(1.0, -2.0, -5.0, 6.0), &[-2.0, 1.0, 3.0]);
+  }
+
+  #[test]
+  fn cubic_one_real_root() {
+    // (x - 2)(x^2 + 1)
+    assert_roots(Gem::cubic_roots(2.0, -4.0, 2.0, -4.0), &[2.0]);
+  }
+
+  #[test]
+  fn cubic_near_degenerate_leading_coefficient() {
+    // (x - 1)(x - 2) with the tiny cubic part, the third root is far away
+    let roots = Gem::cubic_roots(1e-12, 1.0, -3.0, 2.0);
+    assert_roots(roots[1..].to_vec(), &[1.0, 2.0]);
+    assert!((roots[0] / -1e12 - 1.0).abs() < 1e-6, "roots {:?}", roots);
+  }
+
+  #[test]
+  fn quartic_four_distinct_roots() {
+    // (x + 3)(x + 1)(x - 2)(x - 4)
+    assert_roots(Gem::quartic_roots(1.0, -2.0, -13.0, 14.0, 24.0), &[-3.0, -1.0, 2.0, 4.0]);
+  }
+
+  #[test]
+  fn quartic_double_root() {
+    // (x - 1)^2 (x - 2)(x - 3)
+    assert_roots(distinct(Gem::quartic_roots(1.0, -7.0, 17.0, -17.0, 6.0)), &[1.0, 2.0, 3.0]);
+  }
+
+  #[test]
+  fn quartic_no_real_roots() {
+    // (x^2 + 1)(x^2 + 4)
+    assert_roots(Gem::quartic_roots(1.0, 0.0, 5.0, 0.0, 4.0), &[]);
+    // (x^2 - 2x + 2)(x^2 + 2x + 5), not biquadratic
+    assert_roots(Gem::quartic_roots(1.0, 0.0, 3.0, -6.0, 10.0), &[]);
+  }
+
+  #[test]
+  fn quartic_near_degenerate_leading_coefficient() {
+    // (x + 1)(x - 1)(x - 2) with the tiny quartic part, the fourth root is far away
+    let roots = Gem::quartic_roots(1e-12, 1.0, -2.0, -1.0, 2.0);
+    assert_roots(roots[1..].to_vec(), &[-1.0, 1.0, 2.0]);
+    assert!((roots[0] / -1e12 - 1.0).abs() < 1e-6, "roots {:?}", roots);
+  }
+}
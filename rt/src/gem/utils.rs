@@ -1,53 +1,52 @@
 use crate::debug::append_to_file;
 
-use super::{gem::Gem, spear::Spear, mat::Mat, dot::Dot};
+use super::{gem::Gem, spear::Spear, mat::Mat, dot::Dot, bvh::Bvh, hit::Hit};
 
 impl Gem {
 
-  /// intersection of line and plane. Both directions considered.
-  /// So the ray_x_mat case requires additional check for the ray direction.
-  /// Can be solved by using Dot::offset method, for the plane,
-  /// and then check the distances. It can be little bit tricky
-  pub fn line_x_mat(ray: &Mat, mat: &Mat) -> Dot {
+  /// intersection of ray and plane.
+  /// Only the forward direction along the ray vector is considered.
+  /// 
+  /// The front side of the plane is the side of the plane normal vector.
+  /// 
+  /// If the ray is parallel to the plane, or the plane is behind, than return None
+  pub fn ray_x_mat(ray: &Mat, mat: &Mat) -> Option<Hit> {
     let tup = -(
       mat.normal.x * ray.origin.x +
       mat.normal.y * ray.origin.y +
       mat.normal.z * ray.origin.z +
       mat.d
     );
-    let tdn = (
+    let tdn =
       mat.normal.x * ray.normal.x +
       mat.normal.y * ray.normal.y +
-      mat.normal.z * ray.normal.z
-    );
+      mat.normal.z * ray.normal.z;
 
-    if tdn == 0.0 {return Dot::maximum()}
-    if tup == 0.0 {return ray.origin}
+    if tdn == 0.0 { return None }
 
     let t = tup / tdn;
+    if t < 0.0 { return None }
 
-    Dot::new(
-      ray.origin.x + ray.normal.x * t,
-      ray.origin.y + ray.normal.y * t,
-      ray.origin.z + ray.normal.z * t,
-    )
-
-  }
-
-  /// extend line_x_mat to ray_x_mat.
-  /// By checking the ray direction of hit point non-negative along the ray vector.
-  pub fn ray_x_mat(ray: &Mat, mat: &Mat) -> Dot {
-    let hit = Gem::line_x_mat(ray, mat);
-    if hit.is_maximum() || hit.is_below(ray) {return Dot::maximum()}
-    else {hit}
+    Some(Hit::new(ray, t, mat.normal))
   }
 
   /// intersection of ray and sphere.
   /// 
   /// The first one from ray origin, to forward direction, along the ray vector.
+  /// If the ray origin is inside the ball, than the exit point is returned.
   /// 
-  /// If there is no intersection than return Dot::maximum(), to avoid brainfuck
-  pub fn ray_x_ball(ray: &Mat, center:&Dot, radius:f64) -> Dot {
+  /// If there is no intersection than return None
+  pub fn ray_x_ball(ray: &Mat, center:&Dot, radius:f64) -> Option<Hit> {
+    Hit::forward(Gem::ray_x_ball_both(ray, center, radius))
+  }
+
+  /// both intersections of the ray line and sphere, the near and the far ones.
+  /// 
+  /// The ray parameter of both can be negative(behind the ray origin),
+  /// the near one is negative if the ray origin is inside the ball.
+  /// 
+  /// If there is no intersection than return None
+  pub fn ray_x_ball_both(ray: &Mat, center:&Dot, radius:f64) -> Option<[Hit; 2]> {
     // check an idiot case
     let radius = (radius.xyz().abs()+1.0).xyz()-1.0;
 
@@ -61,231 +60,446 @@ impl Gem {
     let b = 2f64 * (x * ray.normal.x + y * ray.normal.y + z * ray.normal.z);
     let c = x * x + y * y + z * z - radius.powi(2);
 
-    // Calculate the discriminant
-    let d = b * b - 4.0 * a * c;
+    // the roots are sorted, so the first one is the near intersection
+    let roots = Gem::quadratic_roots(a, b, c);
+    let (near, far) = (*roots.first()?, *roots.last()?);
 
-    // If the discriminant is negative, there are no intersections
-    if d < 0.0 { return Dot::maximum() }
-
-    // Calculate the two possible solutions for t
-    let t1 = (-b - d.sqrt()) / (2.0 * a);
-    let t2 = (-b + d.sqrt()) / (2.0 * a);
-
-    // Choose the closer to ray origin (which is zoom point) intersection point
-    let p1 = Dot::new(
-      ray.origin.x + t1 * ray.normal.x,
-      ray.origin.y + t1 * ray.normal.y,
-      ray.origin.z + t1 * ray.normal.z,
-    );
-
-    let p2 = Dot::new(
-      ray.origin.x + t2 * ray.normal.x,
-      ray.origin.y + t2 * ray.normal.y,
-      ray.origin.z + t2 * ray.normal.z,
-    );
-
-    if p1.d_dot(&ray.origin) < p2.d_dot(&ray.origin) {p1} else {p2}
+    // the normal is the vector from the center to the hit position
+    let hit = |t: f64| {
+      let normal = Spear::new(
+        x + t * ray.normal.x,
+        y + t * ray.normal.y,
+        z + t * ray.normal.z,
+      );
+      Hit::new(ray, t, normal)
+    };
+    Some([hit(near), hit(far)])
     
   }
 
-  /// intersection of ray and cube.
+  /// intersection of ray and box(cuboid).
   /// 
-  /// The cube(box) is oriented along axes, it does not break any requirements of the task.
-  /// The target is ray tracing, not cube rotating.
+  /// The box can be rotated, so it is described by the center,
+  /// the size (width, height, depth) along its own local axes,
+  /// and the local axes themselves (unit vectors x, y, z of the box).
   /// 
-  /// the size is the length of the edge of the cube. So it is width, height and depth, in one value.
-  pub fn ray_x_box(ray: &Mat, box_center:&Dot, box_size:f64) -> Dot {
-    // check an idiot case
-    let s = ((box_size.xyz().abs()+1.0).xyz()-1.0).half();
-
-    // create two positions, which will restrict the values for x, y and z
-    // from minimum to maximum
-    let pmin = Dot::new(box_center.x - s, box_center.y - s, box_center.z - s,);
-    let pmax = Dot::new(box_center.x + s, box_center.y + s, box_center.z + s,);
-
-    // create x6 planes, to simulate the cube surface. Each plane is a Mat
-    let px_min = Mat::new( pmin, Spear::ox().back());
-    let px_max = Mat::new( pmax, Spear::ox());
-    let py_min = Mat::new( pmin, Spear::oy().back());
-    let py_max = Mat::new( pmax, Spear::oy());
-    let pz_min = Mat::new( pmin, Spear::oz().back());
-    let pz_max = Mat::new( pmax, Spear::oz());
-
-    // check the ray intersection with each plane
-    let hit_x_min = Gem::line_x_mat(ray, &px_min);
-    let hit_x_max = Gem::line_x_mat(ray, &px_max);
-    let hit_y_min = Gem::line_x_mat(ray, &py_min);
-    let hit_y_max = Gem::line_x_mat(ray, &py_max);
-    let hit_z_min = Gem::line_x_mat(ray, &pz_min);
-    let hit_z_max = Gem::line_x_mat(ray, &pz_max);
-
-    // check the intersection points are on the cube surface,
-    // and return the nearest one(the valid area of each plane
-    // restricted by x4 planes around, but in our case of placement the cube
-    // along axes, enough just restrict the intersecion by min max coordinates)
-    let mut hit = Dot::maximum();
-
-    if hit_x_min.y >= pmin.y && hit_x_min.y <= pmax.y
-    && hit_x_min.z >= pmin.z && hit_x_min.z <= pmax.z
-    && hit.d_dot(&ray.origin) > hit_x_min.d_dot(&ray.origin)
-    {hit = hit_x_min.same()}
-
-    if hit_x_max.y >= pmin.y && hit_x_max.y <= pmax.y
-    && hit_x_max.z >= pmin.z && hit_x_max.z <= pmax.z
-    && hit.d_dot(&ray.origin) > hit_x_max.d_dot(&ray.origin)
-    {hit = hit_x_max.same()}
-
-    if hit_y_min.x >= pmin.x && hit_y_min.x <= pmax.x
-    && hit_y_min.z >= pmin.z && hit_y_min.z <= pmax.z
-    && hit.d_dot(&ray.origin) > hit_y_min.d_dot(&ray.origin)
-    {hit = hit_y_min.same()}
-
-    if hit_y_max.x >= pmin.x && hit_y_max.x <= pmax.x
-    && hit_y_max.z >= pmin.z && hit_y_max.z <= pmax.z
-    && hit.d_dot(&ray.origin) > hit_y_max.d_dot(&ray.origin)
-    {hit = hit_y_max.same()}
-
-    if hit_z_min.x >= pmin.x && hit_z_min.x <= pmax.x
-    && hit_z_min.y >= pmin.y && hit_z_min.y <= pmax.y
-    && hit.d_dot(&ray.origin) > hit_z_min.d_dot(&ray.origin)
-    {hit = hit_z_min.same()}
-
-    if hit_z_max.x >= pmin.x && hit_z_max.x <= pmax.x
-    && hit_z_max.y >= pmin.y && hit_z_max.y <= pmax.y
-    && hit.d_dot(&ray.origin) > hit_z_max.d_dot(&ray.origin)
-    {hit = hit_z_max.same()}
-
-    hit
+  /// The ray is projected to the local axes of the box, and then
+  /// the three pairs of parallel faces (slabs) restrict the ray parameter.
+  /// 
+  /// The first one from ray origin, to forward direction, along the ray vector.
+  /// If the ray origin is inside the box, than the exit point is returned.
+  /// 
+  /// If there is no intersection than return None
+  pub fn ray_x_box(ray: &Mat, box_center:&Dot, box_size:[f64; 3], box_axes:[[f64; 3]; 3]) -> Option<Hit> {
+    Hit::forward(Gem::ray_x_box_both(ray, box_center, box_size, box_axes))
+  }
+
+  /// both intersections of the ray line and box(cuboid), the enter and the exit ones.
+  /// 
+  /// The ray parameter of both can be negative(behind the ray origin),
+  /// the enter one is negative if the ray origin is inside the box.
+  /// 
+  /// If there is no intersection than return None
+  pub fn ray_x_box_both(ray: &Mat, box_center:&Dot, box_size:[f64; 3], box_axes:[[f64; 3]; 3]) -> Option<[Hit; 2]> {
+    // vector from the box center to the ray origin. Components separately, like in ray_x_ball
+    let o = [
+      ray.origin.x - box_center.x,
+      ray.origin.y - box_center.y,
+      ray.origin.z - box_center.z,
+    ];
+    let v = [ray.normal.x, ray.normal.y, ray.normal.z];
+
+    // the ray parameter range, where the ray is inside all the slabs,
+    // and the outward normals of the faces, where the ray enters and exits the box
+    let mut t_enter = f64::min_xyz();
+    let mut t_exit = f64::max_xyz();
+    let mut n_enter = Spear::zero();
+    let mut n_exit = Spear::zero();
+
+    for (axis, size) in box_axes.iter().zip(box_size.iter()) {
+      // check an idiot case
+      let s = ((size.xyz().abs()+1.0).xyz()-1.0).half();
+
+      // ray origin and ray vector projected to the local axis of the box
+      let o_local = o[0] * axis[0] + o[1] * axis[1] + o[2] * axis[2];
+      let v_local = v[0] * axis[0] + v[1] * axis[1] + v[2] * axis[2];
+
+      if v_local == 0.0 {
+        // the ray is parallel to the slab, so it must start between the faces
+        if o_local.abs() > s { return None }
+        continue;
+      }
+
+      // the ray enters the slab through the face against the ray, and exits through the other one
+      let axis = Spear::from_array(*axis);
+      let (t1, t2) = ((-s - o_local) / v_local, (s - o_local) / v_local);
+      let (t_in, t_out) = if t1 < t2 { (t1, t2) } else { (t2, t1) };
+      let n_in = if v_local > 0.0 { axis.back() } else { axis };
+
+      if t_in > t_enter { t_enter = t_in; n_enter = n_in; }
+      if t_out < t_exit { t_exit = t_out; n_exit = n_in.back(); }
+    }
+
+    // the slabs do not overlap along the ray
+    if t_enter > t_exit { return None }
+
+    Some([Hit::new(ray, t_enter, n_enter), Hit::new(ray, t_exit, n_exit)])
 
   }
 
   /// intersection of ray and cylinder.
   ///
-  /// The cylinder is oriented along axes,
-  /// it does not break any requirements of the task.
-  /// The height is along the y axis.
-  /// The target is ray tracing, not cylinder rotating.
+  /// The cylinder is placed along the axis vector, which passes through the center.
+  /// The height is along the axis, half of the height to each side from the center.
+  /// Both ends of the cylinder are closed by flat round gaps(caps).
   /// 
   /// the radius is the radius of the cylinder.
   /// the height is the height of the cylinder.
-  pub fn ray_x_roll(ray: &Mat, roll_center:&Dot, radius:f64, height:f64) -> Dot {
+  /// 
+  /// The first one from ray origin, to forward direction, along the ray vector.
+  /// If the ray origin is inside the roll, than the exit point is returned.
+  /// 
+  /// If there is no intersection than return None
+  pub fn ray_x_roll(ray: &Mat, roll_center:&Dot, radius:f64, height:f64, axis:[f64; 3]) -> Option<Hit> {
+    Hit::forward(Gem::ray_x_roll_both(ray, roll_center, radius, height, axis))
+  }
 
-    let mut nearest_intersection = Dot::maximum();
+  /// both intersections of the ray line and cylinder, the near and the far ones.
+  /// 
+  /// The ray parameter of both can be negative(behind the ray origin),
+  /// the near one is negative if the ray origin is inside the roll.
+  /// 
+  /// If there is no intersection than return None
+  pub fn ray_x_roll_both(ray: &Mat, roll_center:&Dot, radius:f64, height:f64, axis:[f64; 3]) -> Option<[Hit; 2]> {
     // check an idiot case
     let radius = (radius.xyz().abs()+1.0).xyz()-1.0;
     let height = (height.xyz().abs()+1.0).xyz()-1.0;
+    let axis = Spear::from_array(axis);
+    if axis.is_zero() { return None }
+
+    // vector from the roll center to the ray origin. Components separately, like in ray_x_ball
+    let o = [
+      ray.origin.x - roll_center.x,
+      ray.origin.y - roll_center.y,
+      ray.origin.z - roll_center.z,
+    ];
+    let v = [ray.normal.x, ray.normal.y, ray.normal.z];
+    let a = axis.to_array();
+
+    // projections of the ray origin and the ray vector to the roll axis
+    let o_along = o[0] * a[0] + o[1] * a[1] + o[2] * a[2];
+    let v_along = v[0] * a[0] + v[1] * a[1] + v[2] * a[2];
+
+    // parts of the ray origin and the ray vector, perpendicular to the roll axis
+    let o_across = [o[0] - o_along * a[0], o[1] - o_along * a[1], o[2] - o_along * a[2]];
+    let v_across = [v[0] - v_along * a[0], v[1] - v_along * a[1], v[2] - v_along * a[2]];
+
+    // the intersections with the side surface or with the gaps, the roll is convex,
+    // so the nearest and the farthest of them are the enter and the exit
+    let mut hits: Vec<Hit> = Vec::new();
 
-    // first check top and bottom gaps intersection
-
-    // top gap intersection position
-    let top_gap_origin = Dot::new(roll_center.x, roll_center.y + height.half(), roll_center.z);
-    let top_gap_mat = Mat::new(top_gap_origin, Spear::oy());
-    let mut top_gap_xyz = Gem::line_x_mat(ray, &top_gap_mat);
-    // now check the intersection is inside the gap, restricted by radius
-    if top_gap_xyz.d_dot(&top_gap_origin) > radius {top_gap_xyz = Dot::maximum()}
-
-    // bottom gap intersection position
-    let bottom_gap_origin = Dot::new(roll_center.x, roll_center.y - height.half(), roll_center.z);
-    let bottom_gap_mat = Mat::new(bottom_gap_origin, Spear::oy().back());
-    let mut bottom_gap_xyz = Gem::line_x_mat(ray, &bottom_gap_mat);
-    // now check the intersection is inside the gap, restricted by radius
-    if bottom_gap_xyz.d_dot(&bottom_gap_origin) > radius {bottom_gap_xyz = Dot::maximum()}
-
-    // if the both gap intersections are inside the radius than
-    // return the nearest one because the ray hit cylinder through the gaps
-    // else if one of the gap intersections is inside the radius than
-    // set the nearest intersection to this gap intersection
-    let d_top = top_gap_xyz.d_dot(&ray.origin);
-    let d_bottom = bottom_gap_xyz.d_dot(&ray.origin);
-
-    if !top_gap_xyz.is_maximum() && !bottom_gap_xyz.is_maximum() {
-      if d_top < d_bottom {
-        return top_gap_xyz.same()
+    // CHECK THE CYLINDER SIDE(ROLL) SURFACE INTERSECTION
+    // coefficients for the quadratic equation, in the plane perpendicular to the axis
+    let qa = v_across[0].powi(2) + v_across[1].powi(2) + v_across[2].powi(2);
+    let qb = 2.0 * (o_across[0] * v_across[0] + o_across[1] * v_across[1] + o_across[2] * v_across[2]);
+    let qc = o_across[0].powi(2) + o_across[1].powi(2) + o_across[2].powi(2) - radius.powi(2);
+
+    // if the ray is parallel to the axis (qa is zero), then only the gaps can be hit
+    if qa > 0.0 {
+      for t in Gem::quadratic_roots(qa, qb, qc) {
+        // the intersection point must be between the gaps
+        if (o_along + t * v_along).abs() <= height.half() {
+          // the normal is the part of the vector from the center, perpendicular to the axis
+          let normal = Spear::new(
+            o_across[0] + t * v_across[0],
+            o_across[1] + t * v_across[1],
+            o_across[2] + t * v_across[2],
+          );
+          hits.push(Hit::new(ray, t, normal));
+        }
       }
-      else {
-        return bottom_gap_xyz.same()
+    }
+
+    // CHECK THE TOP AND BOTTOM GAPS INTERSECTION
+    if v_along != 0.0 {
+      for (gap, normal) in [(height.half(), axis), (-height.half(), axis.back())] {
+        let t = (gap - o_along) / v_along;
+        // the intersection point must be inside the gap, restricted by radius
+        let across = [
+          o_across[0] + t * v_across[0],
+          o_across[1] + t * v_across[1],
+          o_across[2] + t * v_across[2],
+        ];
+        let r = (across[0].powi(2) + across[1].powi(2) + across[2].powi(2)).sqrt();
+        if r <= radius {
+          hits.push(Hit::new(ray, t, normal));
+        }
       }
-    } else { // at least one of the gap intersections is outside the radius
-      nearest_intersection = if d_top < d_bottom {
-        top_gap_xyz.same()
-      } else {
-        bottom_gap_xyz.same()
+    }
+
+    let near = hits.iter().min_by(|a, b| a.t.total_cmp(&b.t))?;
+    let far = hits.iter().max_by(|a, b| a.t.total_cmp(&b.t))?;
+    Some([*near, *far])
+
+  }
+
+  /// intersection of ray and cone.
+  /// 
+  /// The cone base(round gap) center is the position, the apex is placed
+  /// along the axis vector, on the height distance from the base center.
+  /// The base is closed by flat round gap.
+  /// 
+  /// the radius is the radius of the base.
+  /// the height is the distance from the base center to the apex.
+  /// 
+  /// The first one from ray origin, to forward direction, along the ray vector.
+  /// 
+  /// If there is no intersection than return None
+  pub fn ray_x_cone(ray: &Mat, base_center:&Dot, radius:f64, height:f64, axis:[f64; 3]) -> Option<Hit> {
+    // check an idiot case
+    let radius = (radius.xyz().abs()+1.0).xyz()-1.0;
+    let height = (height.xyz().abs()+1.0).xyz()-1.0;
+    let axis = Spear::from_array(axis);
+    if axis.is_zero() || height == 0.0 { return None }
+
+    // the cone is calculated from the apex, along the vector to the base
+    let w = axis.back().to_array();
+    let apex = base_center.offset(&axis, height);
+
+    // vector from the apex to the ray origin. Components separately, like in ray_x_ball
+    let o = [ray.origin.x - apex.x, ray.origin.y - apex.y, ray.origin.z - apex.z];
+    let v = [ray.normal.x, ray.normal.y, ray.normal.z];
+
+    // projections of the ray origin and the ray vector to the cone axis (from apex to base)
+    let o_along = o[0] * w[0] + o[1] * w[1] + o[2] * w[2];
+    let v_along = v[0] * w[0] + v[1] * w[1] + v[2] * w[2];
+
+    // the side surface: the distance from the axis is (radius / height) * distance along the axis
+    let k = 1.0 + (radius / height).powi(2);
+    let qa = v[0] * v[0] + v[1] * v[1] + v[2] * v[2] - k * v_along * v_along;
+    let qb = 2.0 * (o[0] * v[0] + o[1] * v[1] + o[2] * v[2] - k * o_along * v_along);
+    let qc = o[0] * o[0] + o[1] * o[1] + o[2] * o[2] - k * o_along * o_along;
+
+    let mut nearest: Option<Hit> = None;
+
+    for t in Gem::quadratic_roots(qa, qb, qc) {
+      // the intersection point must be between the apex and the base (not the mirrored cone),
+      // and in the ray direction
+      let along = o_along + t * v_along;
+      if t >= 0.0 && (0.0..=height).contains(&along) {
+        // the gradient of the cone surface equation
+        let normal = Spear::new(
+          o[0] + t * v[0] - k * along * w[0],
+          o[1] + t * v[1] - k * along * w[1],
+          o[2] + t * v[2] - k * along * w[2],
+        );
+        nearest = Hit::nearest(nearest, Some(Hit::new(ray, t, normal)));
       }
     }
 
-    // now, when at least one gap intersection is outside the radius,
-    // CHECK THE CYLINDER SIDE(ROLL) SURFACE INTERSECTION
+    // CHECK THE BASE GAP INTERSECTION
+    let base = Gem::ray_x_mat(ray, &Mat::new(base_center.same(), axis.back()))
+      .filter(|hit| hit.dot.d_dot(base_center) <= radius);
 
-    // roll_axis is the vector along the cylinder axis
-    let roll_axis = Spear::oy();
+    Hit::nearest(nearest, base)
 
-    // normal to both roll_axis and ray vector, to calculate , later, the shortest distance between ray and cylinder axis
-    let normal_to_axes = roll_axis.normal(&ray.normal);
-    // it is ok if this will return zero vector,
-    // finally the distance will be maximum possible inside the limits,
-    // so, with high chance greater than radius.
-    // But case of an idiot(super giant cylinder radius) still possible.
+  }
 
-    // the plane which is parallel to the cylinder axis, contains the cylinder axis, and parallel to the ray
-    let roll_axis_mat = Mat::new(roll_center.same(), normal_to_axes);
+  /// intersection of ray and disk (finite round part of the plane).
+  /// 
+  /// the center is the center of the disk, the normal is the plane normal vector.
+  /// 
+  /// If there is no intersection than return None
+  pub fn ray_x_disk(ray: &Mat, center:&Dot, radius:f64, normal:[f64; 3]) -> Option<Hit> {
+    // check an idiot case
+    let radius = (radius.xyz().abs()+1.0).xyz()-1.0;
 
-    // distance between ray and cylinder axis
-    let distance = ray.origin.d_mat(&roll_axis_mat);
+    Gem::ray_x_mat(ray, &Mat::new(center.same(), Spear::from_array(normal)))
+      .filter(|hit| hit.dot.d_dot(center) <= radius)
+  }
 
-    if distance <= radius {
-      // the ray is close enough to the cylinder axis, so it can hit the cylinder side(roll) surface}
+  /// intersection of ray and torus.
+  /// 
+  /// The torus is the ring around the axis vector, which passes through the center.
+  /// 
+  /// the major radius is the distance from the center to the center of the tube.
+  /// the minor radius is the radius of the tube.
+  /// 
+  /// The quartic equation is solved. To keep the precision, the ray origin is moved
+  /// to the ray position closest to the torus center, before the calculation.
+  /// 
+  /// The first one from ray origin, to forward direction, along the ray vector.
+  /// 
+  /// If there is no intersection than return None
+  pub fn ray_x_torus(ray: &Mat, center:&Dot, major_radius:f64, minor_radius:f64, axis:[f64; 3]) -> Option<Hit> {
+    // check an idiot case
+    let major = (major_radius.xyz().abs()+1.0).xyz()-1.0;
+    let minor = (minor_radius.xyz().abs()+1.0).xyz()-1.0;
+    let axis = Spear::from_array(axis);
+    if axis.is_zero() { return None }
+    let a = axis.to_array();
+
+    let v = [ray.normal.x, ray.normal.y, ray.normal.z];
+    let vv = v[0] * v[0] + v[1] * v[1] + v[2] * v[2];
+    if vv == 0.0 { return None }
+
+    // the ray parameter of the ray position closest to the torus center
+    let t0 = -(
+      (ray.origin.x - center.x) * v[0] +
+      (ray.origin.y - center.y) * v[1] +
+      (ray.origin.z - center.z) * v[2]
+    ) / vv;
+
+    // vector from the torus center to the moved ray origin
+    let o = [
+      ray.origin.x + t0 * v[0] - center.x,
+      ray.origin.y + t0 * v[1] - center.y,
+      ray.origin.z + t0 * v[2] - center.z,
+    ];
+
+    // squared distance from the center: vv*s^2 + ov*s + oo
+    let ov = 2.0 * (o[0] * v[0] + o[1] * v[1] + o[2] * v[2]);
+    let oo = o[0] * o[0] + o[1] * o[1] + o[2] * o[2];
+    // distance along the axis: va*s + oa
+    let va = v[0] * a[0] + v[1] * a[1] + v[2] * a[2];
+    let oa = o[0] * a[0] + o[1] * a[1] + o[2] * a[2];
+
+    // (|p|^2 + R^2 - r^2)^2 = 4 * R^2 * (|p|^2 - (p*a)^2)
+    let k = oo + major * major - minor * minor;
+    let rr4 = 4.0 * major * major;
+    let roots = Gem::quartic_roots(
+      vv * vv,
+      2.0 * vv * ov,
+      ov * ov + 2.0 * vv * k - rr4 * (vv - va * va),
+      2.0 * ov * k - rr4 * (ov - 2.0 * va * oa),
+      k * k - rr4 * (oo - oa * oa),
+    );
 
-      // the plane which is parallel to the cylinder axis and contains ray
-      let ray_mat_ll_cylinder_axis = Mat::new(ray.origin.same(), normal_to_axes);
+    // the nearest forward root, the root is calculated from the moved ray origin
+    let s = roots.into_iter().find(|s| s + t0 >= 0.0)?;
+
+    // the normal is directed from the nearest position of the tube center circle
+    let p = [o[0] + s * v[0], o[1] + s * v[1], o[2] + s * v[2]];
+    let along = p[0] * a[0] + p[1] * a[1] + p[2] * a[2];
+    let across = Spear::new(p[0] - along * a[0], p[1] - along * a[1], p[2] - along * a[2]);
+    let normal = Spear::new(
+      p[0] - major * across.x,
+      p[1] - major * across.y,
+      p[2] - major * across.z,
+    );
 
-      // the plane which is contains the ray and contains the normal_to_axes.
-      // It is the plane which intersects the cylinder axis
-      let mat_of_intersection = Mat::new(ray.origin.same(), ray.normal.normal(&normal_to_axes));
+    Some(Hit::new(ray, s + t0, normal))
 
-      // dot on the cylinder axis, which is the intersection of the cylinder axis and the mat_of_intersection plane
-      let dot_on_cylinder_axis = Gem::line_x_mat(
-        &Mat::new(
-          roll_center.same(),
-          Spear::oy()),
-        &mat_of_intersection
-      );
+  }
 
-      // the dot, which is projection of the dot_on_cylinder_axis on the
-      // ray_mat_ll_cylinder_axis plane. This is the dot on the ray, which is
-      // the center between the two intersection points of the ray and
-      // the cylinder side(roll) surface
-      let dot_on_ray = dot_on_cylinder_axis.p_mat(&ray_mat_ll_cylinder_axis);
-
-      // the mutliplifier distance. when the ray and roll_axis are not perpendicular.
-      // can be negative, but this only revert the order of -d and d usage in offset method bottom
-      let coef =  ray.normal.cos(&roll_axis).acos().sin();
-      
-      // the distance to offset the dot_on_ray(in two directions), to get the intersection point.
-      // Additionally limited by ".xyz()", to manage zero coef without brainfuck
-      let d = (
-        ((radius).powi(2) - distance.powi(2)).sqrt()/coef
-      ).xyz();
-
-      let mut p1 = dot_on_ray.offset(&ray.normal, d);
-      let mut p2 = dot_on_ray.offset(&ray.normal, -d);
-
-      // check the intersection points are inside the cylinder side(roll) surface
-      if p1.y < roll_center.y - height.half() || p1.y > roll_center.y + height.half() {p1 = Dot::maximum()}
-      if p2.y < roll_center.y - height.half() || p2.y > roll_center.y + height.half() {p2 = Dot::maximum()}
-
-      // check the intersection points are in the ray direction
-      if !p1.is_above(ray) {p1 = Dot::maximum()}
-      if !p2.is_above(ray) {p2 = Dot::maximum()}
-
-      // choose the nearest intersection point
-      if p1.d_dot(&ray.origin) < nearest_intersection.d_dot(&ray.origin) {nearest_intersection = p1.same()}
-      if p2.d_dot(&ray.origin) < nearest_intersection.d_dot(&ray.origin) {nearest_intersection = p2.same()}
-      
-    }
-  
+  /// intersection of ray and triangle.
+  /// 
+  /// Möller–Trumbore algorithm, the ray parameter and the barycentric coordinates
+  /// of the hit point are calculated together, without the plane of the triangle.
+  /// Both sides of the triangle are hit. The front side is the side, to see
+  /// the vertices ccw.
+  /// 
+  /// If there is no intersection than return None
+  pub fn ray_x_triangle(ray: &Mat, triangle: &[[f64; 3]; 3]) -> Option<Hit> {
+    let [a, b, c] = *triangle;
+    let v = [ray.normal.x, ray.normal.y, ray.normal.z];
+
+    // edges of the triangle from the first vertex
+    let e1 = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let e2 = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+
+    // vector product of the ray vector and the second edge
+    let p = [
+      v[1] * e2[2] - v[2] * e2[1],
+      v[2] * e2[0] - v[0] * e2[2],
+      v[0] * e2[1] - v[1] * e2[0],
+    ];
+    let det = e1[0] * p[0] + e1[1] * p[1] + e1[2] * p[2];
+
+    // the ray is parallel to the triangle, or the triangle is degenerate.
+    // The determinant scales with the edge lengths, so does the threshold, for the tiny triangles
+    let e1_length = (e1[0] * e1[0] + e1[1] * e1[1] + e1[2] * e1[2]).sqrt();
+    let e2_length = (e2[0] * e2[0] + e2[1] * e2[1] + e2[2] * e2[2]).sqrt();
+    if det.abs() <= f64::EPSILON * e1_length * e2_length { return None }
+
+    // vector from the first vertex to the ray origin
+    let o = [ray.origin.x - a[0], ray.origin.y - a[1], ray.origin.z - a[2]];
+
+    let u = (o[0] * p[0] + o[1] * p[1] + o[2] * p[2]) / det;
+    if !(0.0..=1.0).contains(&u) { return None }
+
+    let q = [
+      o[1] * e1[2] - o[2] * e1[1],
+      o[2] * e1[0] - o[0] * e1[2],
+      o[0] * e1[1] - o[1] * e1[0],
+    ];
+    let w = (v[0] * q[0] + v[1] * q[1] + v[2] * q[2]) / det;
+    if w < 0.0 || u + w > 1.0 { return None }
+
+    // the hit point must be in the ray direction
+    let t = (e2[0] * q[0] + e2[1] * q[1] + e2[2] * q[2]) / det;
+    if t < 0.0 { return None }
+
+    Some(Hit::new(ray, t, Spear::from_array(e1).normal(&Spear::from_array(e2))))
+
+  }
+
+  /// intersection of ray and mesh (set of triangles).
+  /// 
+  /// Only the triangles, which boxes are hit by the ray, are checked.
+  /// The tree of the boxes must be built from the same triangles list.
+  /// 
+  /// The first one from ray origin, to forward direction, along the ray vector.
+  /// 
+  /// If there is no intersection than return None
+  pub fn ray_x_mesh(ray: &Mat, triangles: &[[[f64; 3]; 3]], bvh: &Bvh) -> Option<Hit> {
+    bvh.candidates(ray)
+      .into_iter()
+      .map(|index| Gem::ray_x_triangle(ray, &triangles[index]))
+      .fold(None, Hit::nearest)
+
+  }
 
-    nearest_intersection
+  /// local axes of the object rotated by Euler angles in degrees.
+  /// 
+  /// The rotation is applied around the global x axis first,
+  /// then around the global y axis, then around the global z axis.
+  /// 
+  /// Returns \[local x, local y, local z\] unit vectors as arrays
+  pub fn euler_axes(angles_degrees: [f64; 3]) -> [[f64; 3]; 3] {
+    let (sx, cx) = Gem::radians(angles_degrees[0]).sin_cos();
+    let (sy, cy) = Gem::radians(angles_degrees[1]).sin_cos();
+    let (sz, cz) = Gem::radians(angles_degrees[2]).sin_cos();
+
+    let rotate = |v: [f64; 3]| -> [f64; 3] {
+      // around x
+      let v = [v[0], v[1] * cx - v[2] * sx, v[1] * sx + v[2] * cx];
+      // around y
+      let v = [v[0] * cy + v[2] * sy, v[1], -v[0] * sy + v[2] * cy];
+      // around z
+      let v = [v[0] * cz - v[1] * sz, v[0] * sz + v[1] * cz, v[2]];
+      Spear::from_array(v).to_array()
+    };
+
+    [
+      rotate([1.0, 0.0, 0.0]),
+      rotate([0.0, 1.0, 0.0]),
+      rotate([0.0, 0.0, 1.0]),
+    ]
+  }
 
+  /// local axes of the object, built from the directions of its local x and y axes.
+  /// 
+  /// The y direction is corrected to be perpendicular to the x direction,
+  /// and the z axis is built to see ccw from x to y.
+  /// 
+  /// Returns None if the directions are parallel, or one of them is zero
+  pub fn pair_axes(x_direction: [f64; 3], y_direction: [f64; 3]) -> Option<[[f64; 3]; 3]> {
+    let x = Spear::from_array(x_direction);
+    let z = x.normal(&Spear::from_array(y_direction));
+    if x.is_zero() || z.is_zero() { return None }
+    let y = z.normal(&x);
+    Some([x.to_array(), y.to_array(), z.to_array()])
   }
 
   /// convert radians to degrees
@@ -337,4 +551,30 @@ impl F64xyz for f64 {
 
   fn half(self) -> f64 { self * 0.5 }
 
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// the triangle around the z axis in the plane z = 0, with the size of the edges
+  fn triangle(size: f64) -> [[f64; 3]; 3] {
+    [[-size, -size, 0.0], [size, -size, 0.0], [0.0, size, 0.0]]
+  }
+
+  #[test]
+  fn ray_x_tiny_triangle() {
+    let ray = Mat::new(Dot::new(0.0, 0.0, -1.0), Spear::new(0.0, 0.0, 1.0));
+    for size in [1e3, 1.0, 1e-3, 1e-9] {
+      let hit = Gem::ray_x_triangle(&ray, &triangle(size));
+      assert!(hit.is_some_and(|hit| (hit.t - 1.0).abs() < 1e-9), "size {}", size);
+    }
+  }
+
+  #[test]
+  fn ray_along_triangle() {
+    let ray = Mat::new(Dot::new(0.0, 0.0, -1.0), Spear::new(1.0, 0.0, 0.0));
+    assert!(Gem::ray_x_triangle(&ray, &triangle(1.0)).is_none());
+    let ray = Mat::new(Dot::new(-2.0, 0.0, 0.0), Spear::new(1.0, 0.0, 0.0));
+    assert!(Gem::ray_x_triangle(&ray, &triangle(1.0)).is_none());
+  }
+}
@@ -5,52 +5,73 @@ mod parser {
   pub mod camera_file;
   pub mod light_file;
   pub mod objects_file;
+  pub mod mesh_file;
 }
 
 mod tracer {
   pub mod camera;
+  pub mod adaptive;
   pub mod check;
+  pub mod glass;
+  pub mod light;
   pub mod pixel_;
-  pub mod pixel_mat;
-  pub mod pixel_ball;
-  pub mod pixel_box;
-  pub mod pixel_roll;
+  pub mod passes;
+  pub mod sample;
   pub mod scene;
+  pub mod shadow;
   pub mod trace;
 }
 
 mod printer {
   pub mod ppm;
+  pub mod png;
+  pub mod pfm;
+  pub mod hdr;
+  pub mod tone;
 }
 
 use std::env;
 use std::process;
-use parser::camera_file::Camera;
+use parser::camera_file::{Camera, PpmFormat};
 use parser::light_file::Light;
 use parser::objects_file::Objects;
 use printer::ppm::generate_ppm_file;
+use printer::png::generate_png_file;
+use printer::pfm::generate_pfm_file;
+use printer::hdr::generate_hdr_file;
+use printer::tone::tone_map;
 use debug:: try_recreate_file_according_to_value_of_debug_boolean;
 
 fn main() {
   let _ = try_recreate_file_according_to_value_of_debug_boolean();
 
   let args: Vec<String> = env::args().collect();
-  if args.len() != 4 {
-    println!("Usage: ./we <camera_file> <light_file> <objects_file>");
+  if args.len() != 4 && args.len() != 5 {
+    println!("Usage: ./we <camera_file> <light_file> <objects_file> [p3|p6|p6_16]");
     process::exit(1);
   }
   
   let camera_file = &args[1];
-  let camera = match Camera::parse_from_file(camera_file) {
+  let mut camera = match Camera::parse_from_file(camera_file) {
     Ok(camera) => camera,
     Err(error) => {
       panic!("Problem parsing the camera file: {}", error);
     }
   };
   
+  // the output format from the command line is used instead of the camera file one
+  if let Some(format) = args.get(4) {
+    camera.format = match PpmFormat::parse(format) {
+      Ok(format) => format,
+      Err(error) => {
+        panic!("Problem parsing the output format: {}", error);
+      }
+    };
+  }
+  
   let light_file = &args[2];
-  let light = match Light::parse_from_file(light_file) {
-    Ok(light) => light,
+  let lights = match Light::parse_from_file(light_file) {
+    Ok(lights) => lights,
     Err(error) => {
       panic!("Problem parsing the light file: {}", error);
     }
@@ -65,16 +86,38 @@ fn main() {
   };
   
   println!("Camera: {:?}", camera);
-  println!("Light: {:?}", light);
+  println!("Lights: {:?}", lights);
   println!("Objects: {:?}", objects);
   
-  let scene = tracer::scene::Scene::new(camera, light, objects);
+  let scene = tracer::scene::Scene::new(camera, lights, objects);
+  for warning in scene.camera_orientation_warnings() {
+    println!("Warning: {}", warning);
+  }
   
-  generate_ppm_file(
-    scene.camera.width as u32,
-    scene.camera.height as u32, 
-    &scene.camera.output_file_name, 
-    &scene.trace()
-  ).unwrap();
+  let frame = scene.trace();
+  let camera = &scene.camera;
+  write_picture(camera, &camera.output_file_name, &frame.radiance, |radiance| tone_map(radiance, camera));
+  // each pass is saved next to the main picture, with the suffix in the name
+  for (pass, values) in camera.passes.iter().zip(&frame.passes) {
+    write_picture(camera, &pass.file_name(&camera.output_file_name), values, |values| pass.display(values));
+  }
   
+}
+
+/// save the picture, the kind is chosen by the file name extension.
+///
+/// The float pictures keep the values as is, the other ones are converted to the brightness(0 to 1) by the display
+fn write_picture(camera: &Camera, file_name: &String, values: &[f64], display: impl Fn(&[f64]) -> Vec<f64>) {
+  let width = camera.width as u32;
+  let height = camera.height as u32;
+  let result = if file_name.ends_with(".pfm") {
+    generate_pfm_file(width, height, file_name, values)
+  } else if file_name.ends_with(".hdr") {
+    generate_hdr_file(width, height, file_name, values)
+  } else if file_name.ends_with(".png") {
+    generate_png_file(width, height, file_name, &display(values))
+  } else {
+    generate_ppm_file(width, height, file_name, &display(values), camera.format)
+  };
+  result.unwrap();
 }
\ No newline at end of file
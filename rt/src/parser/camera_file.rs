@@ -2,16 +2,110 @@ use std::fs::File;
 use std::io::{BufRead, BufReader};
 
 const MAX_OUTPUT_PICTURE_SIDE_SIZE:usize  = 1024;
+/// number of the reflections of the ray, if the depth is not specified
+const DEFAULT_DEPTH: usize = 4;
+/// limit of the reflections of the ray, to prevent endless render
+const MAX_DEPTH: usize = 64;
+/// limit of the rays through each pixel, to prevent endless render
+const MAX_SAMPLES: usize = 256;
+/// limit of the subdivisions of the edge pixels, each one makes up to 4 times more rays
+const MAX_ADAPTIVE: usize = 4;
+/// number of the rays through each pixel with the lens, if the samples are not specified
+const LENS_SAMPLES: usize = 16;
+
+/// the way the rays are spread inside the pixel, if there are more than one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SamplePattern {
+  /// the centers of the cells of the regular grid, the number of the rays must be square(4, 9, 16 ...)
+  Grid,
+  /// the random points anywhere in the pixel
+  Jittered,
+  /// the random point in each cell of the regular grid, the number of the rays must be square(4, 9, 16 ...)
+  Stratified,
+}
+
+/// the kind of the output PPM file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PpmFormat {
+  /// text, the numbers of each pixel on the own line, compatible with any viewer, but big and slow
+  P3,
+  /// binary, one byte per color component
+  P6,
+  /// binary, two bytes per color component(maxval 65535), keeps more precision
+  P6Wide,
+}
+
+impl PpmFormat {
+  /// the format from the camera file format line or the command line
+  pub fn parse(word: &str) -> Result<PpmFormat, String> {
+    match word {
+      "p3" => Ok(PpmFormat::P3),
+      "p6" => Ok(PpmFormat::P6),
+      "p6_16" => Ok(PpmFormat::P6Wide),
+      _ => Err(format!("Format must be p3, p6 or p6_16: {}", word)),
+    }
+  }
+}
+
+/// the way the radiance(not limited) of the pixels is compressed to the picture brightness(0 to 1)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToneMap {
+  /// the brighter than 1 is 1, the colors of the bright parts are lost
+  Clamp,
+  /// x / (1 + x), the bright parts are compressed smoothly, the whole picture is darker
+  Reinhard,
+  /// the S-curve like the film(ACES approximation), the contrast midtones and the soft highlights
+  Filmic,
+}
+
+/// the extra picture rendered with the main one, from the nearest hits of the rays through the pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pass {
+  /// the distance from the ray start to the hit
+  Depth,
+  /// the unit normal vector of the surface in the hit, directed to the camera
+  Normal,
+  /// the index of the hit object, in the list of the objects good to trace
+  Id,
+  /// the part of the light hidden from the hit by the other objects
+  Shadow,
+}
+
+/// the way the rays go from the camera
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+  /// from the zoom position(or the camera position with the fov) through the pixels, the far objects are smaller
+  Perspective,
+  /// parallel to the camera vector, from the pixels, one pixel is one unit, the far objects are the same size
+  Orthographic,
+  /// from the camera position to all the directions around, the columns are the angles around the up vector,
+  /// the rows are the angles from the up vector to the down
+  Equirectangular,
+}
 
 #[derive(Debug)]
 pub struct Camera {
   pub width: usize,
   pub height: usize,
   pub output_file_name: String,
-  pub zoom: usize, // displacement of rays start point from camera position to back
+  pub zoom: usize, // displacement of rays start point from camera position to back, 0 if the fov is used
+  pub fov: f64, // horizontal field of view in degrees, the rays start from the camera position, 0 if the zoom is used
   pub position: [f64; 3],
   pub look_at: [f64; 3], // point in the front of the camera, to build camera vector
   pub up: [f64; 3],
+  pub roll: f64, // angle in degrees, the camera is turned clockwise around the camera vector
+  pub depth: usize, // maximum number of the reflections of the ray, 0 is no reflections
+  pub samples: usize, // number of the rays through each pixel, their colors are averaged
+  pub pattern: SamplePattern,
+  pub adaptive: usize, // maximum number of the subdivisions of the edge pixels, 0 is no adaptive anti-aliasing
+  pub aperture: f64, // diameter of the lens around the zoom position, 0 is the pinhole without blur
+  pub focus: f64, // distance from the camera position to the sharp plane, along the camera vector
+  pub projection: Projection,
+  pub format: PpmFormat,
+  pub tone: ToneMap,
+  pub exposure: f64, // stops, the radiance is multiplied by 2 to the power of the exposure before the tone mapping
+  pub gamma: Option<f64>, // the picture brightness is the tone mapped value to the power of 1 / gamma, None is the sRGB encoding
+  pub passes: Vec<Pass>, // the extra pictures, saved next to the main one
 }
 impl Camera {
   pub fn parse_from_file(file_path: &str) -> Result<Camera, String> {
@@ -22,15 +116,46 @@ impl Camera {
     let mut height = 0;
     let mut output_file_name = "".to_string();
     let mut zoom = 0;
+    let mut fov = 0.0;
     let mut position = [0f64; 3];
     let mut look_at = [0f64; 3];
     let mut up = [0f64; 3];
+    let mut yaw = 0.0;
+    let mut pitch = 0.0;
+    let mut roll = 0.0;
+    let mut depth = DEFAULT_DEPTH;
+    let mut samples = 1;
+    let mut pattern = SamplePattern::Grid;
+    let mut adaptive = 0;
+    let mut aperture = 0.0;
+    let mut focus = 0.0;
+    let mut projection = Projection::Perspective;
+    let mut format = PpmFormat::P3;
+    let mut tone = ToneMap::Clamp;
+    let mut exposure = 0.0;
+    let mut gamma = None;
+    let mut passes = Vec::new();
 
     let mut zoom_parsed = false;
+    let mut fov_parsed = false;
     let mut from_parsed = false;
     let mut to_parsed = false;
     let mut up_parsed = false;
+    let mut yaw_parsed = false;
+    let mut pitch_parsed = false;
+    let mut roll_parsed = false;
     let mut output_parsed = false;
+    let mut depth_parsed = false;
+    let mut samples_parsed = false;
+    let mut adaptive_parsed = false;
+    let mut aperture_parsed = false;
+    let mut focus_parsed = false;
+    let mut projection_parsed = false;
+    let mut format_parsed = false;
+    let mut tone_parsed = false;
+    let mut exposure_parsed = false;
+    let mut gamma_parsed = false;
+    let mut passes_parsed = false;
     
     for line in reader.lines() {
       let line = line.map_err(|e| e.to_string())?;
@@ -39,6 +164,15 @@ impl Camera {
         continue;
       }
       match words[0] {
+        "fov" if !fov_parsed && words.len() == 2 => {
+          fov = words[1].parse::<f64>().map_err(|_| {
+            "Fov must be a number of degrees between 0 and 180".to_string()
+          })?;
+          if fov <= 0.0 || fov >= 180.0 {
+            return Err("Fov must be a number of degrees between 0 and 180".to_string());
+          }
+          fov_parsed = true;
+        }
         "zoom" if !zoom_parsed && words.len() == 2 => {
           zoom = words[1].parse::<usize>().map_err(|_| {
             "Zoom must be greater than zero".to_string()
@@ -76,6 +210,132 @@ impl Camera {
           ];
           up_parsed = true;
         }
+        "yaw" if !yaw_parsed && words.len() == 2 => {
+          yaw = words[1].parse::<f64>().map_err(|_| "Yaw must be a number of degrees".to_string())?;
+          yaw_parsed = true;
+        }
+        "pitch" if !pitch_parsed && words.len() == 2 => {
+          pitch = words[1].parse::<f64>().map_err(|_| {
+            "Pitch must be a number of degrees between -90 and 90".to_string()
+          })?;
+          if pitch <= -90.0 || pitch >= 90.0 {
+            return Err("Pitch must be a number of degrees between -90 and 90".to_string());
+          }
+          pitch_parsed = true;
+        }
+        "roll" if !roll_parsed && words.len() == 2 => {
+          roll = words[1].parse::<f64>().map_err(|_| "Roll must be a number of degrees".to_string())?;
+          roll_parsed = true;
+        }
+        "depth" if !depth_parsed && words.len() == 2 => {
+          depth = words[1].parse::<usize>().map_err(|_| {
+            format!("Depth must be an integer between 0 and {}", MAX_DEPTH)
+          })?;
+          if depth > MAX_DEPTH {
+            return Err(format!("Depth must be an integer between 0 and {}", MAX_DEPTH));
+          }
+          depth_parsed = true;
+        }
+        "samples" if !samples_parsed && (words.len() == 2 || words.len() == 3) => {
+          samples = words[1].parse::<usize>().map_err(|_| {
+            format!("Samples must be an integer between 1 and {}", MAX_SAMPLES)
+          })?;
+          if !(1..=MAX_SAMPLES).contains(&samples) {
+            return Err(format!("Samples must be an integer between 1 and {}", MAX_SAMPLES));
+          }
+          if words.len() == 3 {
+            pattern = match words[2] {
+              "grid" => SamplePattern::Grid,
+              "jittered" => SamplePattern::Jittered,
+              "stratified" => SamplePattern::Stratified,
+              _ => return Err(format!("Samples pattern must be grid, jittered or stratified: {}", line)),
+            };
+          }
+          let side = (samples as f64).sqrt().round() as usize;
+          if pattern != SamplePattern::Jittered && side * side != samples {
+            return Err(format!("Samples of the grid and stratified patterns must be a square number(1, 4, 9, 16 ...): {}", line));
+          }
+          samples_parsed = true;
+        }
+        "adaptive" if !adaptive_parsed && words.len() == 2 => {
+          adaptive = words[1].parse::<usize>().map_err(|_| {
+            format!("Adaptive must be an integer between 0 and {}", MAX_ADAPTIVE)
+          })?;
+          if adaptive > MAX_ADAPTIVE {
+            return Err(format!("Adaptive must be an integer between 0 and {}", MAX_ADAPTIVE));
+          }
+          adaptive_parsed = true;
+        }
+        "aperture" if !aperture_parsed && words.len() == 2 => {
+          aperture = words[1].parse::<f64>().map_err(|_| {
+            "Aperture must be a number not less than 0".to_string()
+          })?;
+          if aperture < 0.0 {
+            return Err("Aperture must be a number not less than 0".to_string());
+          }
+          aperture_parsed = true;
+        }
+        "focus" if !focus_parsed && words.len() == 2 => {
+          focus = words[1].parse::<f64>().map_err(|_| {
+            "Focus must be a number greater than 0".to_string()
+          })?;
+          if focus <= 0.0 {
+            return Err("Focus must be a number greater than 0".to_string());
+          }
+          focus_parsed = true;
+        }
+        "projection" if !projection_parsed && words.len() == 2 => {
+          projection = match words[1] {
+            "perspective" => Projection::Perspective,
+            "orthographic" => Projection::Orthographic,
+            "equirectangular" => Projection::Equirectangular,
+            _ => return Err(format!("Projection must be perspective, orthographic or equirectangular: {}", line)),
+          };
+          projection_parsed = true;
+        }
+        "format" if !format_parsed && words.len() == 2 => {
+          format = PpmFormat::parse(words[1])?;
+          format_parsed = true;
+        }
+        "tone" if !tone_parsed && words.len() == 2 => {
+          tone = match words[1] {
+            "clamp" => ToneMap::Clamp,
+            "reinhard" => ToneMap::Reinhard,
+            "filmic" => ToneMap::Filmic,
+            _ => return Err(format!("Tone must be clamp, reinhard or filmic: {}", line)),
+          };
+          tone_parsed = true;
+        }
+        "exposure" if !exposure_parsed && words.len() == 2 => {
+          exposure = words[1].parse::<f64>().map_err(|_| "Exposure must be a number of stops".to_string())?;
+          exposure_parsed = true;
+        }
+        "gamma" if !gamma_parsed && words.len() == 2 => {
+          let value = words[1].parse::<f64>().map_err(|_| {
+            "Gamma must be a number greater than 0".to_string()
+          })?;
+          if value <= 0.0 {
+            return Err("Gamma must be a number greater than 0".to_string());
+          }
+          gamma = Some(value);
+          gamma_parsed = true;
+        }
+        "passes" if !passes_parsed && words.len() > 1 => {
+          for word in &words[1..] {
+            let pass = match *word {
+              "depth" => Pass::Depth,
+              "normal" => Pass::Normal,
+              "id" => Pass::Id,
+              "shadow" => Pass::Shadow,
+              _ => return Err(format!("Passes must be depth, normal, id or shadow: {}", line)),
+            };
+            if passes.contains(&pass) {
+              return Err(format!("Each pass can be used only once: {}", line));
+            }
+            passes.push(pass);
+          }
+          passes_parsed = true;
+        }
         "#" => {}
         _ if words.len() == 3 && !output_parsed => {
           width = words[0].parse::<usize>().map_err(|_| {
@@ -107,30 +367,178 @@ impl Camera {
         }
       }
     }
-    if !zoom_parsed {
+    // only the perspective needs the distance from the rays start to the screen
+    if projection == Projection::Perspective && !zoom_parsed && !fov_parsed {
       return Err(
         format!(
-          "Camera zoom [1..{}] not specified", MAX_OUTPUT_PICTURE_SIDE_SIZE.pow(2)
+          "Camera zoom [1..{}] or fov [degrees] not specified", MAX_OUTPUT_PICTURE_SIDE_SIZE.pow(2)
         )
       );
     }
+    if zoom_parsed && fov_parsed {
+      return Err("Only one of the zoom and fov can be used".to_string());
+    }
     if !from_parsed {
       return Err("Camera position [from x y z] not specified".to_string());
     }
-    if !to_parsed {
-      return Err("Look at point [to x y z] not specified".to_string());
+    let angles_parsed = yaw_parsed || pitch_parsed;
+    if to_parsed && angles_parsed {
+      return Err("Only one of the look at point [to x y z] and the angles [yaw] [pitch] can be used".to_string());
+    }
+    if !to_parsed && !angles_parsed {
+      return Err("Look at point [to x y z] or angles [yaw] [pitch] not specified".to_string());
     }
-    if !up_parsed {
+    if angles_parsed {
+      // yaw 0 and pitch 0 look along -z, the positive yaw turns to the left, the positive pitch turns up.
+      // The look at point is one unit away from the camera position, in the direction
+      let (yaw, pitch) = (yaw.to_radians(), pitch.to_radians());
+      let direction = [-yaw.sin() * pitch.cos(), pitch.sin(), -yaw.cos() * pitch.cos()];
+      look_at = [position[0] + direction[0], position[1] + direction[1], position[2] + direction[2]];
+      // the up vector is built from the camera position, so the default is one unit above it
+      if !up_parsed {
+        up = [position[0], position[1] + 1.0, position[2]];
+      }
+      if aperture > 0.0 && !focus_parsed {
+        return Err("Focus [distance] must be specified with the aperture and the angles [yaw] [pitch]".to_string());
+      }
+    } else if !up_parsed {
       return Err("Up vector end point [up x y z] not specified".to_string());
     }
     if !output_parsed {
       return Err("Output file [width height name] not specified".to_string());
     }
+    if samples > 1 && adaptive > 0 {
+      return Err("Only one of the samples and adaptive can be used".to_string());
+    }
+    if aperture > 0.0 {
+      if projection == Projection::Equirectangular {
+        return Err("Aperture can not be used with the equirectangular projection".to_string());
+      }
+      if adaptive > 0 {
+        return Err("Only one of the aperture and adaptive can be used".to_string());
+      }
+      // the blur needs many rays through each pixel
+      if !samples_parsed {
+        samples = LENS_SAMPLES;
+        pattern = SamplePattern::Stratified;
+      }
+    }
+    // the sharp plane goes through the point the camera is looking at
+    if !focus_parsed {
+      focus = position.iter().zip(look_at).map(|(p, l)| (l - p).powi(2)).sum::<f64>().sqrt();
+    }
 
     let camera = Camera {
-      width, height, output_file_name, zoom, position, look_at, up
+      width, height, output_file_name, zoom, fov, position, look_at, up, roll,
+      depth, samples, pattern, adaptive, aperture, focus, projection, format,
+      tone, exposure, gamma, passes
     };
 
     Ok(camera)
   }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+
+  /// the camera parsed from the lines, saved to the own temporary file of each call
+  fn parse(text: &str) -> Result<Camera, String> {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let path = std::env::temp_dir().join(format!(
+      "rt_camera_test_{}_{}",
+      std::process::id(),
+      NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&path, text).unwrap();
+    let camera = Camera::parse_from_file(&path.to_string_lossy());
+    std::fs::remove_file(&path).unwrap();
+    camera
+  }
+
+  /// the smallest camera file, the lines of the test are added to it
+  fn parse_with(lines: &str) -> Result<Camera, String> {
+    parse(&format!("40 30 x.ppm\nfrom 0 0 10\nto 0 0 0\nup 0 1 10\n{}\n", lines))
+  }
+
+  #[test]
+  fn defaults() {
+    let camera = parse_with("zoom 50").unwrap();
+    assert_eq!((camera.width, camera.height, camera.zoom), (40, 30, 50));
+    assert_eq!((camera.samples, camera.pattern, camera.adaptive), (1, SamplePattern::Grid, 0));
+    assert_eq!((camera.aperture, camera.focus), (0.0, 10.0));
+    assert_eq!(camera.projection, Projection::Perspective);
+    assert_eq!((camera.tone, camera.exposure, camera.gamma), (ToneMap::Clamp, 0.0, None));
+    assert!(camera.passes.is_empty());
+  }
+
+  #[test]
+  fn samples_and_pattern() {
+    let camera = parse_with("zoom 50\nsamples 9 stratified").unwrap();
+    assert_eq!((camera.samples, camera.pattern), (9, SamplePattern::Stratified));
+    assert_eq!(parse_with("zoom 50\nsamples 5 jittered").unwrap().samples, 5);
+    assert!(parse_with("zoom 50\nsamples 5").is_err());
+    assert!(parse_with("zoom 50\nsamples 0").is_err());
+    assert!(parse_with("zoom 50\nsamples 4 random").is_err());
+  }
+
+  #[test]
+  fn adaptive_without_samples() {
+    assert_eq!(parse_with("zoom 50\nadaptive 2").unwrap().adaptive, 2);
+    assert!(parse_with("zoom 50\nadaptive 2\nsamples 4").is_err());
+    assert!(parse_with(&format!("zoom 50\nadaptive {}", MAX_ADAPTIVE + 1)).is_err());
+  }
+
+  #[test]
+  fn aperture_and_focus() {
+    // the lens samples by default, and the focus on the look at point
+    let camera = parse_with("zoom 50\naperture 2").unwrap();
+    assert_eq!((camera.samples, camera.pattern), (LENS_SAMPLES, SamplePattern::Stratified));
+    assert_eq!(camera.focus, 10.0);
+    let camera = parse_with("zoom 50\naperture 2\nfocus 4\nsamples 4").unwrap();
+    assert_eq!((camera.samples, camera.focus), (4, 4.0));
+    assert!(parse_with("zoom 50\naperture -1").is_err());
+    assert!(parse_with("zoom 50\nfocus 0").is_err());
+    assert!(parse_with("zoom 50\naperture 2\nadaptive 1").is_err());
+  }
+
+  #[test]
+  fn fov_or_zoom() {
+    assert_eq!(parse_with("fov 60").unwrap().fov, 60.0);
+    assert!(parse_with("fov 60\nzoom 50").is_err());
+    assert!(parse_with("fov 180").is_err());
+    assert!(parse_with("").is_err());
+  }
+
+  #[test]
+  fn projections() {
+    // only the perspective needs the zoom or fov
+    assert_eq!(parse_with("projection orthographic").unwrap().projection, Projection::Orthographic);
+    assert_eq!(parse_with("projection equirectangular").unwrap().projection, Projection::Equirectangular);
+    assert!(parse_with("projection equirectangular\naperture 1").is_err());
+    assert!(parse_with("projection fisheye").is_err());
+  }
+
+  #[test]
+  fn angles_and_roll() {
+    let camera = parse("40 30 x.ppm\nzoom 50\nfrom 1 2 3\nyaw 90\npitch 0\nroll 15\n").unwrap();
+    // the positive yaw turns to the left, from -z to -x, the up vector is above the camera
+    let expected = [0.0, 2.0, 3.0];
+    assert!(camera.look_at.iter().zip(expected).all(|(a, e)| (a - e).abs() < 1e-9), "{:?}", camera.look_at);
+    assert_eq!(camera.up, [1.0, 3.0, 3.0]);
+    assert_eq!(camera.roll, 15.0);
+    assert!(parse("40 30 x.ppm\nzoom 50\nfrom 1 2 3\nto 0 0 0\nyaw 90\n").is_err());
+    assert!(parse("40 30 x.ppm\nzoom 50\nfrom 1 2 3\npitch 90\n").is_err());
+    assert!(parse("40 30 x.ppm\nzoom 50\nfrom 1 2 3\nyaw 10\naperture 1\n").is_err());
+  }
+
+  #[test]
+  fn output_keywords() {
+    let camera = parse_with("zoom 50\ntone filmic\nexposure -1.5\ngamma 2.2\npasses depth id").unwrap();
+    assert_eq!((camera.tone, camera.exposure, camera.gamma), (ToneMap::Filmic, -1.5, Some(2.2)));
+    assert_eq!(camera.passes, [Pass::Depth, Pass::Id]);
+    assert!(parse_with("zoom 50\npasses depth depth").is_err());
+    assert!(parse_with("zoom 50\ngamma 0").is_err());
+    assert!(parse_with("zoom 50\ntone aces").is_err());
+  }
+}
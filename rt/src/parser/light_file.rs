@@ -1,31 +1,73 @@
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
+use crate::gem::spear::Spear;
+
 // Define Light struct
 #[derive(Debug)]
 pub struct Light {
   // Define fields for Light
-  pub power: f64,
   pub color: [u8; 3],
-  pub position: [f64; 3],
+  pub kind: LightKind,
+  /// number of the points on the area light surface, to cast the shadow rays to.
+  /// Other kinds of the light use one point
+  pub samples: usize,
+}
+
+/// number of the points on the area light surface, if the samples line is not specified
+const AREA_SAMPLES: usize = 16;
+
+/// the way the light shines
+#[derive(Debug, Clone, Copy)]
+pub enum LightKind {
+  /// lamp in the position, shines up to the power distance, the light fades out to it
+  Lamp { power: f64, position: [f64; 3], shape: LampShape },
+  /// the sun far away, shines with parallel rays along the direction(unit vector),
+  /// without the position and the power distance limit
+  Sun { direction: [f64; 3] },
+}
+
+/// the shape of the lamp, the position is its center
+#[derive(Debug, Clone, Copy)]
+pub enum LampShape {
+  /// shines in all directions
+  Point,
+  /// shines inside the cone around the direction(unit vector).
+  /// The angle is between the cone axis and the cone side, in degrees.
+  /// The light fades out after the angle, through the edge, in degrees
+  Spot { direction: [f64; 3], angle: f64, edge: f64 },
+  /// the flat rectangle around the position, shines in all directions.
+  /// The edges are the vectors along the rectangle sides, with the length of the sides
+  Rect { edge_u: [f64; 3], edge_v: [f64; 3] },
+  /// the ball around the position, shines in all directions
+  Sphere { radius: f64 },
+}
+
+/// parsed values of the current light block, None if not parsed yet
+#[derive(Default)]
+struct LightBlock {
+  power: Option<f64>,
+  color: Option<[u8; 3]>,
+  position: Option<[f64; 3]>,
+  shape: Option<LampShape>,
+  /// the sun direction
+  sun: Option<[f64; 3]>,
+  samples: Option<usize>,
 }
 
 // Implement Light parser
 impl Light {
-  // Define a function to parse Light from file
-  pub fn parse_from_file(file_path: &str) -> Result<Light, String> {
+  // Define a function to parse Lights from file.
+  // Each light is the block of power, color and from lines(plus optional spot, rect or sphere line,
+  // and optional samples line), in any order, or the block of color and sun lines.
+  // The repeated line, or the line of the other kind of the light, starts the next light block
+  pub fn parse_from_file(file_path: &str) -> Result<Vec<Light>, String> {
     let file = File::open(file_path).map_err(|e| e.to_string())?;
     let reader = BufReader::new(file);
     
-    // Initialize variables to store parsed values
-    let mut power = 0.0;
-    let mut color = [0; 3];
-    let mut position = [0.0; 3];
+    let mut lights = Vec::new();
     
-    // Flags to check if parameters are parsed
-    let mut power_parsed = false;
-    let mut color_parsed = false;
-    let mut from_parsed = false;
+    let mut block = LightBlock::default();
     
     for line in reader.lines() {
       let line = line.map_err(|e| e.to_string())?;
@@ -33,28 +75,100 @@ impl Light {
       if words.is_empty() {
         continue;
       }
+      
+      // the line already parsed in the current block, or the line not used by the current kind of the light,
+      // finishes the block
+      let is_sun = block.sun.is_some();
+      let repeated = match words[0] {
+        "power" => block.power.is_some() || is_sun,
+        "color" => block.color.is_some(),
+        "from" => block.position.is_some() || is_sun,
+        "samples" => block.samples.is_some(),
+        "spot" | "rect" | "sphere" => block.shape.is_some() || is_sun,
+        "sun" => is_sun || block.shape.is_some() || block.power.is_some() || block.position.is_some() || block.samples.is_some(),
+        _ => false,
+      };
+      if repeated {
+        lights.push(Light::from_parsed(block)?);
+        block = LightBlock::default();
+      }
+      
       match words[0] {
-        "power" if !power_parsed && words.len() == 2 => {
-          power = words[1].parse::<f64>().map_err(|_| {
+        "power" if words.len() == 2 => {
+          block.power = Some(words[1].parse::<f64>().map_err(|_| {
             "Power must be a positive integer".to_string()
-          })?;
-          power_parsed = true;
+          })?);
         }
-        "color" if !color_parsed && words.len() == 4 => {
-          color = [
+        "color" if words.len() == 4 => {
+          block.color = Some([
           words[1].parse::<u8>().map_err(|_| "Color R must be an integer 0 to 255".to_string())?,
           words[2].parse::<u8>().map_err(|_| "Color G must be an integer 0 to 255".to_string())?,
           words[3].parse::<u8>().map_err(|_| "Color B must be an integer 0 to 255".to_string())?,
-          ];
-          color_parsed = true;
+          ]);
         }
-        "from" if !from_parsed && words.len() == 4 => {
-          position = [
+        "from" if words.len() == 4 => {
+          block.position = Some([
           words[1].parse::<f64>().map_err(|_| "Position x must be an integer".to_string())?,
           words[2].parse::<f64>().map_err(|_| "Position y must be an integer".to_string())?,
           words[3].parse::<f64>().map_err(|_| "Position z must be an integer".to_string())?,
+          ]);
+        }
+        "sun" if words.len() == 4 => {
+          let direction = Spear::from_array([
+          words[1].parse::<f64>().map_err(|_| "Sun direction x must be an integer".to_string())?,
+          words[2].parse::<f64>().map_err(|_| "Sun direction y must be an integer".to_string())?,
+          words[3].parse::<f64>().map_err(|_| "Sun direction z must be an integer".to_string())?,
+          ]);
+          if direction.is_zero() {
+            return Err(format!("Sun direction must not be zero: {}", line));
+          }
+          block.sun = Some(direction.to_array());
+        }
+        "spot" if words.len() == 6 => {
+          let direction = Spear::from_array([
+          words[1].parse::<f64>().map_err(|_| "Spot direction x must be an integer".to_string())?,
+          words[2].parse::<f64>().map_err(|_| "Spot direction y must be an integer".to_string())?,
+          words[3].parse::<f64>().map_err(|_| "Spot direction z must be an integer".to_string())?,
+          ]);
+          let angle = words[4].parse::<f64>().map_err(|_| "Spot angle must be a number of degrees".to_string())?;
+          let edge = words[5].parse::<f64>().map_err(|_| "Spot edge must be a number of degrees".to_string())?;
+          if direction.is_zero() {
+            return Err(format!("Spot direction must not be zero: {}", line));
+          }
+          if angle <= 0.0 || edge < 0.0 || angle + edge > 180.0 {
+            return Err(format!("Spot angle must be positive, edge must not be negative, and both together not more than 180 degrees: {}", line));
+          }
+          block.shape = Some(LampShape::Spot { direction: direction.to_array(), angle, edge });
+        }
+        "rect" if words.len() == 7 => {
+          let edge_u = [
+          words[1].parse::<f64>().map_err(|_| "Rect edge u x must be an integer".to_string())?,
+          words[2].parse::<f64>().map_err(|_| "Rect edge u y must be an integer".to_string())?,
+          words[3].parse::<f64>().map_err(|_| "Rect edge u z must be an integer".to_string())?,
           ];
-          from_parsed = true;
+          let edge_v = [
+          words[4].parse::<f64>().map_err(|_| "Rect edge v x must be an integer".to_string())?,
+          words[5].parse::<f64>().map_err(|_| "Rect edge v y must be an integer".to_string())?,
+          words[6].parse::<f64>().map_err(|_| "Rect edge v z must be an integer".to_string())?,
+          ];
+          if Spear::from_array(edge_u).is_zero() || Spear::from_array(edge_v).is_zero() {
+            return Err(format!("Rect edges must not be zero: {}", line));
+          }
+          block.shape = Some(LampShape::Rect { edge_u, edge_v });
+        }
+        "sphere" if words.len() == 2 => {
+          let radius = words[1].parse::<f64>().map_err(|_| "Sphere radius must be a positive integer".to_string())?;
+          if radius <= 0.0 {
+            return Err(format!("Sphere radius must be a positive integer: {}", line));
+          }
+          block.shape = Some(LampShape::Sphere { radius });
+        }
+        "samples" if words.len() == 2 => {
+          let samples = words[1].parse::<usize>().map_err(|_| "Samples must be a positive integer".to_string())?;
+          if samples == 0 {
+            return Err(format!("Samples must be a positive integer: {}", line));
+          }
+          block.samples = Some(samples);
         }
         "#" => {}
         _ => {
@@ -63,19 +177,113 @@ impl Light {
       }
     }
     
-    if !power_parsed {
-      return Err("Light power [positive integer distance] is not specified".to_string());
+    // the last block
+    lights.push(Light::from_parsed(block)?);
+    
+    Ok(lights)
+    
+  }
+  
+  /// the light from the parsed values of the block, all of them must be parsed,
+  /// except the shape(point light by default) and the samples(the rect and sphere lights only).
+  /// The sun needs the color only
+  fn from_parsed(block: LightBlock) -> Result<Light, String> {
+    if let Some(direction) = block.sun {
+      let Some(color) = block.color else {
+        return Err("Sun color [color 0-255 0-255 0-255] is not specified".to_string());
+      };
+      if block.samples.is_some() {
+        return Err("Sun samples are not supported, only the rect and sphere lights have samples".to_string());
+      }
+      return Ok(Light { color, kind: LightKind::Sun { direction }, samples: 1 });
     }
     
-    if !color_parsed {
+    let Some(power) = block.power else {
+      return Err("Light power [positive integer distance] is not specified".to_string());
+    };
+    
+    let Some(color) = block.color else {
       return Err("Light color [color 0-255 0-255 0-255] is not specified".to_string());
-    }
+    };
     
-    if !from_parsed {
+    let Some(position) = block.position else {
       return Err("Light position [from x y z] is not specified".to_string());
-    }
+    };
     
-    Ok(Light { power, color, position, })
+    let shape = block.shape.unwrap_or(LampShape::Point);
+    let samples = match (shape, block.samples) {
+      (LampShape::Rect { .. } | LampShape::Sphere { .. }, samples) => samples.unwrap_or(AREA_SAMPLES),
+      (_, None) => 1,
+      (_, Some(_)) => {
+        return Err("Point and spot light samples are not supported, only the rect and sphere lights have samples".to_string());
+      }
+    };
     
+    Ok(Light { color, kind: LightKind::Lamp { power, position, shape }, samples })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+
+  /// the lights parsed from the lines, saved to the own temporary file of each call
+  fn parse(text: &str) -> Result<Vec<Light>, String> {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let path = std::env::temp_dir().join(format!(
+      "rt_light_test_{}_{}",
+      std::process::id(),
+      NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&path, text).unwrap();
+    let lights = Light::parse_from_file(&path.to_string_lossy());
+    std::fs::remove_file(&path).unwrap();
+    lights
+  }
+
+  #[test]
+  fn blocks_in_any_order() {
+    let lights = parse("power 100\ncolor 255 0 0\nfrom 1 2 3\n# the second one\nfrom 4 5 6\npower 50\ncolor 0 0 255\n").unwrap();
+    assert_eq!(lights.len(), 2);
+    assert!(matches!(lights[0].kind, LightKind::Lamp { power: 100.0, position: [1.0, 2.0, 3.0], shape: LampShape::Point }));
+    assert!(matches!(lights[1].kind, LightKind::Lamp { power: 50.0, position: [4.0, 5.0, 6.0], shape: LampShape::Point }));
+    assert_eq!(lights[1].color, [0, 0, 255]);
+    assert!(parse("power 100\ncolor 255 0 0\n").is_err());
+    assert!(parse("power 100\nfrom 1 2 3\n").is_err());
+  }
+
+  #[test]
+  fn sun_and_spot() {
+    let lights = parse("color 255 255 255\nsun 0 -2 0\npower 100\ncolor 1 2 3\nfrom 0 0 0\nspot 0 -1 0 30 10\n").unwrap();
+    assert_eq!(lights.len(), 2);
+    // the sun direction is the unit vector, the sun has no position and power
+    assert!(matches!(lights[0].kind, LightKind::Sun { direction: [0.0, -1.0, 0.0] }));
+    let LightKind::Lamp { shape: LampShape::Spot { angle, edge, .. }, .. } = lights[1].kind else {
+      panic!("the spot light expected: {:?}", lights[1]);
+    };
+    assert_eq!((angle, edge), (30.0, 10.0));
+    assert!(parse("sun 0 -1 0\n").is_err());
+    assert!(parse("color 255 255 255\nsun 0 0 0\n").is_err());
+    assert!(parse("power 100\ncolor 1 2 3\nfrom 0 0 0\nspot 0 -1 0 170 20\n").is_err());
+  }
+
+  #[test]
+  fn area_light_samples() {
+    let lights = parse("power 100\ncolor 1 2 3\nfrom 0 0 0\nrect 2 0 0 0 0 2\npower 100\ncolor 1 2 3\nfrom 0 0 0\nsphere 5\nsamples 4\n").unwrap();
+    assert_eq!((lights[0].samples, lights[1].samples), (AREA_SAMPLES, 4));
+    assert!(matches!(lights[1].kind, LightKind::Lamp { shape: LampShape::Sphere { radius: 5.0 }, .. }));
+    assert!(parse("power 100\ncolor 1 2 3\nfrom 0 0 0\nsphere 5\nsamples 0\n").is_err());
+    assert!(parse("power 100\ncolor 1 2 3\nfrom 0 0 0\nrect 0 0 0 0 0 2\n").is_err());
+  }
+
+  #[test]
+  fn samples_of_not_area_lights() {
+    let point = parse("power 100\ncolor 1 2 3\nfrom 0 0 0\nsamples 4\n").unwrap_err();
+    assert!(point.contains("only the rect and sphere"), "{}", point);
+    let spot = parse("power 100\ncolor 1 2 3\nfrom 0 0 0\nspot 0 -1 0 30 10\nsamples 4\n").unwrap_err();
+    assert!(spot.contains("only the rect and sphere"), "{}", spot);
+    let sun = parse("color 1 2 3\nsun 0 -1 0\nsamples 4\n").unwrap_err();
+    assert!(sun.contains("only the rect and sphere"), "{}", sun);
   }
 }
@@ -0,0 +1,166 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use crate::gem::bvh::Bvh;
+
+/// triangles of the model, loaded from the Wavefront OBJ file
+pub struct Mesh {
+  /// triangles in the global coordinates, each one is x3 vertices
+  pub triangles: Vec<[[f64; 3]; 3]>,
+  /// minimal x y z of the vertices, the corner of the box around the mesh
+  pub min: [f64; 3],
+  /// maximal x y z of the vertices, the corner of the box around the mesh
+  pub max: [f64; 3],
+  /// tree of the boxes around the triangles
+  pub bvh: Bvh,
+}
+
+/// the triangles are not printed, there can be thousands of them
+impl fmt::Debug for Mesh {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Mesh")
+    .field("triangles", &self.triangles.len())
+    .field("min", &self.min)
+    .field("max", &self.max)
+    .finish()
+  }
+}
+
+impl Mesh {
+  /// parse vertices (v) and faces (f) of the OBJ file. Other lines are ignored.
+  ///
+  /// The vertices are scaled, and then moved to the position.
+  /// The faces with more than x3 vertices are split to triangles (fan from the first vertex).
+  pub fn parse_from_file(file_path: &str, position: [f64; 3], scale: f64) -> Result<Mesh, String> {
+    let file = File::open(file_path).map_err(|e| format!("{}: {}", file_path, e))?;
+    let reader = BufReader::new(file);
+
+    let mut vertices: Vec<[f64; 3]> = Vec::new();
+    let mut triangles: Vec<[[f64; 3]; 3]> = Vec::new();
+
+    for line in reader.lines() {
+      let line = line.map_err(|e| e.to_string())?;
+      let words: Vec<&str> = line.split_whitespace().collect();
+      if words.is_empty() {
+        continue;
+      }
+      match words[0] {
+        "v" if words.len() >= 4 => {
+          let mut v = [0f64; 3];
+          for (i, word) in words[1..4].iter().enumerate() {
+            let value = word.parse::<f64>().map_err(|_| {
+              format!("Vertex coordinate must be a number: {}", line)
+            })?;
+            v[i] = position[i] + value * scale;
+          }
+          vertices.push(v);
+        }
+        "f" if words.len() >= 4 => {
+          // the face vertex can be "v", "v/vt", "v//vn" or "v/vt/vn", only "v" is used.
+          // Negative index is counted from the end of the already parsed vertices
+          let mut face = Vec::new();
+          for word in &words[1..] {
+            let index = word.split('/').next().unwrap_or("").parse::<i64>().map_err(|_| {
+              format!("Face vertex index must be an integer: {}", line)
+            })?;
+            let index = if index < 0 { vertices.len() as i64 + index } else { index - 1 };
+            if index < 0 || index >= vertices.len() as i64 {
+              return Err(format!("Face vertex index is out of range: {}", line));
+            }
+            face.push(vertices[index as usize]);
+          }
+          for i in 1..face.len() - 1 {
+            triangles.push([face[0], face[i], face[i + 1]]);
+          }
+        }
+        _ => {}
+      }
+    }
+
+    if triangles.is_empty() {
+      return Err(format!("No faces found in the mesh file: {}", file_path));
+    }
+
+    let mut min = [f64::MAX; 3];
+    let mut max = [f64::MIN; 3];
+    for vertex in triangles.iter().flatten() {
+      for i in 0..3 {
+        min[i] = min[i].min(vertex[i]);
+        max[i] = max[i].max(vertex[i]);
+      }
+    }
+
+    let bounds: Vec<_> = triangles
+    .iter()
+    .map(|[a, b, c]| {
+      Some([
+        [a[0].min(b[0]).min(c[0]), a[1].min(b[1]).min(c[1]), a[2].min(b[2]).min(c[2])],
+        [a[0].max(b[0]).max(c[0]), a[1].max(b[1]).max(c[1]), a[2].max(b[2]).max(c[2])],
+      ])
+    })
+    .collect();
+    let bvh = Bvh::new(&bounds);
+
+    Ok(Mesh { triangles, min, max, bvh })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+
+  /// the mesh parsed from the lines, saved to the own temporary file of each call
+  fn parse(text: &str, position: [f64; 3], scale: f64) -> Result<Mesh, String> {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let path = std::env::temp_dir().join(format!(
+      "rt_mesh_test_{}_{}.obj",
+      std::process::id(),
+      NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&path, text).unwrap();
+    let mesh = Mesh::parse_from_file(&path.to_string_lossy(), position, scale);
+    std::fs::remove_file(&path).unwrap();
+    mesh
+  }
+
+  const SQUARE: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n";
+
+  #[test]
+  fn fan_from_first_vertex() {
+    // the pentagon is split to three triangles, all of them start from the first vertex
+    let mesh = parse("v 0 0 0\nv 2 0 0\nv 3 1 0\nv 1 2 0\nv -1 1 0\nf 1 2 3 4 5\n", [0.0; 3], 1.0).unwrap();
+    let first = [0.0; 3];
+    assert_eq!(
+      mesh.triangles,
+      [
+        [first, [2.0, 0.0, 0.0], [3.0, 1.0, 0.0]],
+        [first, [3.0, 1.0, 0.0], [1.0, 2.0, 0.0]],
+        [first, [1.0, 2.0, 0.0], [-1.0, 1.0, 0.0]],
+      ]
+    );
+  }
+
+  #[test]
+  fn face_vertex_forms_and_negative_index() {
+    let text = format!("{}vt 0 0\nvn 0 0 1\nf 1/1/1 2//1 3/1\nf -4 -2 -1\n", SQUARE);
+    let mesh = parse(&text, [0.0; 3], 1.0).unwrap();
+    assert_eq!(mesh.triangles.len(), 2);
+    assert_eq!(mesh.triangles[1], [[0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]]);
+  }
+
+  #[test]
+  fn scaled_and_moved() {
+    let mesh = parse(&format!("{}f 1 2 3 4\n", SQUARE), [10.0, 0.0, -5.0], 2.0).unwrap();
+    assert_eq!(mesh.triangles.len(), 2);
+    assert_eq!((mesh.min, mesh.max), ([10.0, 0.0, -5.0], [12.0, 2.0, -5.0]));
+  }
+
+  #[test]
+  fn bad_faces() {
+    assert!(parse(&format!("{}f 1 2 5\n", SQUARE), [0.0; 3], 1.0).is_err());
+    assert!(parse(&format!("{}f 1 2 x\n", SQUARE), [0.0; 3], 1.0).is_err());
+    assert!(parse(SQUARE, [0.0; 3], 1.0).is_err());
+  }
+}
@@ -1,190 +1,404 @@
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
 
-// Define Object struct
+use crate::gem::{gem::Gem, spear::Spear};
+
+use super::mesh_file::Mesh;
+
+/// the words of the object line, which start the material properties
+const MATERIAL_KEYWORDS: [&str; 6] = ["specular", "shininess", "reflect", "transparency", "ior", "tint"];
+
+/// surface properties of the object, except the color.
+///
+/// Optional part of the object line, after the object properties,
+/// as the keywords followed by the values, f.e. "specular 0.5 shininess 32 tint 200 255 200"
 #[derive(Debug, Clone, Copy)]
+pub struct Material {
+  /// brightness of the highlight (Blinn-Phong) in range 0-1. Zero is matte surface
+  pub specular: f64,
+  /// size of the highlight, the bigger exponent is the smaller and sharper highlight
+  pub shininess: f64,
+  /// part of the color, taken from the mirror reflection, in range 0-1. Zero is no reflection
+  pub reflect: f64,
+  /// part of the color, taken from the light come through the object(glass), in range 0-1.
+  /// Zero is opaque object
+  pub transparency: f64,
+  /// index of refraction of the transparent object, f.e. 1.5 for the glass, 1.33 for the water
+  pub ior: f64,
+  /// r g b color filter of the light come through the transparent object, 255 255 255 is clear
+  pub tint: [u8; 3],
+}
+
+impl Material {
+  /// matte surface, the same as the object line without the material properties
+  pub fn matte() -> Material {
+    Material {
+      specular: 0.0,
+      shininess: 32.0,
+      reflect: 0.0,
+      transparency: 0.0,
+      ior: 1.5,
+      tint: [255; 3],
+    }
+  }
+
+  /// parse the keywords and the values, the missing ones are default
+  pub fn parse_from_words(words: &[&str]) -> Result<Material, String> {
+    let mut material = Material::matte();
+    let mut words = words.iter();
+    while let Some(keyword) = words.next() {
+      // the next value of the keyword
+      let mut value = || {
+        words.next().ok_or_else(|| {
+          format!("Material property must have a value: {}", keyword)
+        })
+      };
+      match *keyword {
+        "specular" => {
+          material.specular = value()?.parse::<f64>().map_err(|_| {
+            "Specular must be a number 0 to 1".to_string()
+          })?;
+          if !(0.0..=1.0).contains(&material.specular) {
+            return Err("Specular must be a number 0 to 1".to_string());
+          }
+        }
+        "shininess" => {
+          material.shininess = value()?.parse::<f64>().map_err(|_| {
+            "Shininess must be a positive number".to_string()
+          })?;
+          if material.shininess <= 0.0 {
+            return Err("Shininess must be a positive number".to_string());
+          }
+        }
+        "reflect" => {
+          material.reflect = value()?.parse::<f64>().map_err(|_| {
+            "Reflect must be a number 0 to 1".to_string()
+          })?;
+          if !(0.0..=1.0).contains(&material.reflect) {
+            return Err("Reflect must be a number 0 to 1".to_string());
+          }
+        }
+        "transparency" => {
+          material.transparency = value()?.parse::<f64>().map_err(|_| {
+            "Transparency must be a number 0 to 1".to_string()
+          })?;
+          if !(0.0..=1.0).contains(&material.transparency) {
+            return Err("Transparency must be a number 0 to 1".to_string());
+          }
+        }
+        "ior" => {
+          material.ior = value()?.parse::<f64>().map_err(|_| {
+            "Index of refraction must be a positive number".to_string()
+          })?;
+          if material.ior <= 0.0 {
+            return Err("Index of refraction must be a positive number".to_string());
+          }
+        }
+        "tint" => {
+          material.tint = [
+            value()?.parse::<u8>().map_err(|_| {
+              "Tint R must be an integer 0 to 255".to_string()
+            })?,
+            value()?.parse::<u8>().map_err(|_| {
+              "Tint G must be an integer 0 to 255".to_string()
+            })?,
+            value()?.parse::<u8>().map_err(|_| {
+              "Tint B must be an integer 0 to 255".to_string()
+            })?,
+          ];
+        }
+        _ => return Err(format!("Unknown material property: {}", keyword)),
+      }
+    }
+    Ok(material)
+  }
+}
+
+// Define Object struct
+#[derive(Debug, Clone)]
 pub enum Objects {
-    Ball {
-        color: [u8; 3],
-        position: [f64; 3],
-        radius: f64,
-    },
-    Box {
-        color: [u8; 3],
-        position: [f64; 3],
-        size: f64,
-    },
-    Roll {
-        color: [u8; 3],
-        position: [f64; 3],
-        radius: f64,
-        height: f64,
-    },
-    Mat {
-        color: [u8; 3],
-        position: [f64; 3],
-        normal: [f64; 3],
-    },
+  Ball {
+    color: [u8; 3],
+    material: Material,
+    position: [f64; 3],
+    radius: f64,
+  },
+  Box {
+    color: [u8; 3],
+    material: Material,
+    position: [f64; 3],
+    /// width, height and depth, along the local axes of the box
+    size: [f64; 3],
+    /// unit vectors of the local x, y, z axes of the box
+    axes: [[f64; 3]; 3],
+  },
+  Roll {
+    color: [u8; 3],
+    material: Material,
+    position: [f64; 3],
+    radius: f64,
+    height: f64,
+    /// unit vector of the roll axis, the height is along it
+    axis: [f64; 3],
+  },
+  Mat {
+    color: [u8; 3],
+    material: Material,
+    position: [f64; 3],
+    normal: [f64; 3],
+  },
+  Cone {
+    color: [u8; 3],
+    material: Material,
+    /// center of the base of the cone
+    position: [f64; 3],
+    /// radius of the base of the cone
+    radius: f64,
+    /// distance from the base center to the apex, along the axis
+    height: f64,
+    /// unit vector of the cone axis, from the base to the apex
+    axis: [f64; 3],
+  },
+  Disk {
+    color: [u8; 3],
+    material: Material,
+    position: [f64; 3],
+    radius: f64,
+    normal: [f64; 3],
+  },
+  Torus {
+    color: [u8; 3],
+    material: Material,
+    position: [f64; 3],
+    /// distance from the torus center to the center of the tube
+    major_radius: f64,
+    /// radius of the tube
+    minor_radius: f64,
+    /// unit vector of the torus axis, the ring is around it
+    axis: [f64; 3],
+  },
+  Mesh {
+    color: [u8; 3],
+    material: Material,
+    /// triangles already scaled and moved to the position
+    mesh: Arc<Mesh>,
+  },
+}
+
+impl Objects {
+  /// the material of any object
+  pub fn material(&self) -> Material {
+    match self {
+      Objects::Ball { material, .. }
+      | Objects::Box { material, .. }
+      | Objects::Roll { material, .. }
+      | Objects::Mat { material, .. }
+      | Objects::Cone { material, .. }
+      | Objects::Disk { material, .. }
+      | Objects::Torus { material, .. }
+      | Objects::Mesh { material, .. } => *material,
+    }
+  }
+
+  /// the color of any object
+  pub fn color(&self) -> [u8; 3] {
+    match self {
+      Objects::Ball { color, .. }
+      | Objects::Box { color, .. }
+      | Objects::Roll { color, .. }
+      | Objects::Mat { color, .. }
+      | Objects::Cone { color, .. }
+      | Objects::Disk { color, .. }
+      | Objects::Torus { color, .. }
+      | Objects::Mesh { color, .. } => *color,
+    }
+  }
 }
 
 // Implement Object parser
 impl Objects {
-    // Define a function to parse Object from file
-    pub fn parse_from_file(file_path: &str) -> Result<Vec<Objects>, String> {
-        let file = File::open(file_path).map_err(|e| e.to_string())?;
-        let reader = BufReader::new(file);
-
-        let mut objects = Vec::new();
-
-        for line in reader.lines() {
-            let line = line.map_err(|e| e.to_string())?;
-            let words: Vec<&str> = line.split_whitespace().collect();
-            if words.is_empty() || words[0] == "#" || words.len() < 8 {
-                continue;
-            }
-            match words[3] {
-                "ball" if words.len() == 8 => {
-                    let color = [
-                        words[0].parse::<u8>().map_err(|_| {
-                            "Color R must be an integer 0 to 255".to_string()
-                        })?,
-                        words[1].parse::<u8>().map_err(|_| {
-                            "Color G must be an integer 0 to 255".to_string()
-                        })?,
-                        words[2].parse::<u8>().map_err(|_| {
-                            "Color B must be an integer 0 to 255".to_string()
-                        })?,
-                    ];
-                    let position = [
-                        words[4].parse::<f64>().map_err(|_| {
-                            "Position x must be an integer".to_string()
-                        })?,
-                        words[5].parse::<f64>().map_err(|_| {
-                            "Position y must be an integer".to_string()
-                        })?,
-                        words[6].parse::<f64>().map_err(|_| {
-                            "Position z must be an integer".to_string()
-                        })?,
-                    ];
-                    let radius = words[7].parse::<f64>().map_err(|_| {
-                        "Radius must be an integer".to_string()
-                    })?;
-                    objects.push(Objects::Ball {
-                        color,
-                        position,
-                        radius,
-                    });
-                }
-                "box" if words.len() == 8 => {
-                    let color = [
-                        words[0].parse::<u8>().map_err(|_| {
-                            "Color R must be an integer 0 to 255".to_string()
-                        })?,
-                        words[1].parse::<u8>().map_err(|_| {
-                            "Color G must be an integer 0 to 255".to_string()
-                        })?,
-                        words[2].parse::<u8>().map_err(|_| {
-                            "Color B must be an integer 0 to 255".to_string()
-                        })?,
-                    ];
-                    let position = [
-                        words[4].parse::<f64>().map_err(|_| {
-                            "Position x must be an integer".to_string()
-                        })?,
-                        words[5].parse::<f64>().map_err(|_| {
-                            "Position y must be an integer".to_string()
-                        })?,
-                        words[6].parse::<f64>().map_err(|_| {
-                            "Position z must be an integer".to_string()
-                        })?,
-                    ];
-                    let size = words[7].parse::<f64>().map_err(|_| {
-                        "Size must be an integer".to_string()
-                    })?;
-                    objects.push(Objects::Box {
-                        color,
-                        position,
-                        size,
-                    });
-                }
-                "roll" if words.len() == 9 => {
-                    let color = [
-                        words[0].parse::<u8>().map_err(|_| {
-                            "Color R must be an integer 0 to 255".to_string()
-                        })?,
-                        words[1].parse::<u8>().map_err(|_| {
-                            "Color G must be an integer 0 to 255".to_string()
-                        })?,
-                        words[2].parse::<u8>().map_err(|_| {
-                            "Color B must be an integer 0 to 255".to_string()
-                        })?,
-                    ];
-                    let position = [
-                        words[4].parse::<f64>().map_err(|_| {
-                            "Position x must be an integer".to_string()
-                        })?,
-                        words[5].parse::<f64>().map_err(|_| {
-                            "Position y must be an integer".to_string()
-                        })?,
-                        words[6].parse::<f64>().map_err(|_| {
-                            "Position z must be an integer".to_string()
-                        })?,
-                    ];
-                    let radius = words[7].parse::<f64>().map_err(|_| {
-                        "Radius must be an integer".to_string()
-                    })?;
-                    let height = words[8].parse::<f64>().map_err(|_| {
-                        "Height must be an integer".to_string()
-                    })?;
-                    objects.push(Objects::Roll {
-                        color,
-                        position,
-                        radius,
-                        height,
-                    });
-                }
-                "mat" if words.len() == 10 => {
-                    let color = [
-                        words[0].parse::<u8>().map_err(|_| {
-                            "Color R must be an integer 0 to 255".to_string()
-                        })?,
-                        words[1].parse::<u8>().map_err(|_| {
-                            "Color G must be an integer 0 to 255".to_string()
-                        })?,
-                        words[2].parse::<u8>().map_err(|_| {
-                            "Color B must be an integer 0 to 255".to_string()
-                        })?,
-                    ];
-                    let position = [
-                        words[4].parse::<f64>().map_err(|_| {
-                            "Position x must be an integer".to_string()
-                        })?,
-                        words[5].parse::<f64>().map_err(|_| {
-                            "Position y must be an integer".to_string()
-                        })?,
-                        words[6].parse::<f64>().map_err(|_| {
-                            "Position z must be an integer".to_string()
-                        })?,
-                    ];
-                    let normal = [
-                        words[7].parse::<f64>().map_err(|_| {
-                            "Normal x must be an integer".to_string()
-                        })?,
-                        words[8].parse::<f64>().map_err(|_| {
-                            "Normal y must be an integer".to_string()
-                        })?,
-                        words[9].parse::<f64>().map_err(|_| {
-                            "Normal z must be an integer".to_string()
-                        })?,
-                    ];
-                    objects.push(Objects::Mat { color, position, normal });
-                }
-                _ => {
-                    return Err(format!("Unknown command: {}", line));
-                }
-            }
-        }
+  // Define a function to parse Object from file
+  pub fn parse_from_file(file_path: &str) -> Result<Vec<Objects>, String> {
+    let file = File::open(file_path).map_err(|e| e.to_string())?;
+    let reader = BufReader::new(file);
+
+    let mut objects = Vec::new();
 
-        Ok(objects)
+    for line in reader.lines() {
+      let line = line.map_err(|e| e.to_string())?;
+      let words: Vec<&str> = line.split_whitespace().collect();
+      if words.is_empty() || words[0] == "#" || words.len() < 8 {
+        continue;
+      }
+      // the material properties are after the object properties (and after the mesh file path)
+      let split = (5..words.len())
+        .find(|i| MATERIAL_KEYWORDS.contains(&words[*i]))
+        .unwrap_or(words.len());
+      let material = Material::parse_from_words(&words[split..])
+        .map_err(|e| format!("{}: {}", e, line))?;
+      let words = &words[..split];
+      match words[3] {
+        "ball" if words.len() == 8 => {
+          objects.push(Objects::Ball {
+            color: parse_color(&words[0..3])?,
+            material,
+            position: parse_f64s(&words[4..7], "Position")?,
+            radius: parse_f64(words[7], "Radius")?,
+          });
+        }
+        "box" if [8, 10, 13, 16].contains(&words.len()) => {
+          // one size is the cube, three sizes are width, height and depth
+          let size = if words.len() == 8 {
+            [parse_f64(words[7], "Size")?; 3]
+          } else {
+            [
+              parse_f64(words[7], "Width")?,
+              parse_f64(words[8], "Height")?,
+              parse_f64(words[9], "Depth")?,
+            ]
+          };
+          // no orientation, or Euler angles, or directions of local x and y axes
+          let axes = match words.len() {
+            13 => Gem::euler_axes(parse_f64s(&words[10..13], "Angle")?),
+            16 => Gem::pair_axes(
+              parse_f64s(&words[10..13], "Axis x direction")?,
+              parse_f64s(&words[13..16], "Axis y direction")?,
+            )
+            .ok_or_else(|| format!("Box axis directions must not be zero or parallel: {}", line))?,
+            _ => Gem::euler_axes([0.0; 3]),
+          };
+          objects.push(Objects::Box {
+            color: parse_color(&words[0..3])?,
+            material,
+            position: parse_f64s(&words[4..7], "Position")?,
+            size,
+            axes,
+          });
+        }
+        "roll" if words.len() == 9 || words.len() == 12 => {
+          // the roll is vertical (along y axis), if the axis is not specified
+          let axis = parse_axis(words)?;
+          if axis.is_zero() {
+            return Err(format!("Roll axis must not be zero: {}", line));
+          }
+          objects.push(Objects::Roll {
+            color: parse_color(&words[0..3])?,
+            material,
+            position: parse_f64s(&words[4..7], "Position")?,
+            radius: parse_f64(words[7], "Radius")?,
+            height: parse_f64(words[8], "Height")?,
+            axis: axis.to_array(),
+          });
+        }
+        "mat" if words.len() == 10 => {
+          objects.push(Objects::Mat {
+            color: parse_color(&words[0..3])?,
+            material,
+            position: parse_f64s(&words[4..7], "Position")?,
+            normal: parse_f64s(&words[7..10], "Normal")?,
+          });
+        }
+        "cone" if words.len() == 9 || words.len() == 12 => {
+          // the cone is vertical (along y axis), if the axis is not specified
+          let axis = parse_axis(words)?;
+          if axis.is_zero() {
+            return Err(format!("Cone axis must not be zero: {}", line));
+          }
+          objects.push(Objects::Cone {
+            color: parse_color(&words[0..3])?,
+            material,
+            position: parse_f64s(&words[4..7], "Position")?,
+            radius: parse_f64(words[7], "Radius")?,
+            height: parse_f64(words[8], "Height")?,
+            axis: axis.to_array(),
+          });
+        }
+        "disk" if words.len() == 11 => {
+          let radius = parse_f64(words[7], "Radius")?;
+          if radius <= 0.0 {
+            return Err(format!("Disk radius must be a positive number: {}", line));
+          }
+          let normal = Spear::from_array(parse_f64s(&words[8..11], "Normal")?);
+          if normal.is_zero() {
+            return Err(format!("Disk normal must not be zero: {}", line));
+          }
+          objects.push(Objects::Disk {
+            color: parse_color(&words[0..3])?,
+            material,
+            position: parse_f64s(&words[4..7], "Position")?,
+            radius,
+            normal: normal.to_array(),
+          });
+        }
+        "torus" if words.len() == 9 || words.len() == 12 => {
+          // the torus axis is vertical (along y axis), if the axis is not specified
+          let axis = parse_axis(words)?;
+          if axis.is_zero() {
+            return Err(format!("Torus axis must not be zero: {}", line));
+          }
+          objects.push(Objects::Torus {
+            color: parse_color(&words[0..3])?,
+            material,
+            position: parse_f64s(&words[4..7], "Position")?,
+            major_radius: parse_f64(words[7], "Major radius")?,
+            minor_radius: parse_f64(words[8], "Minor radius")?,
+            axis: axis.to_array(),
+          });
+        }
+        "mesh" if words.len() == 9 => {
+          let position = parse_f64s(&words[5..8], "Position")?;
+          let scale = parse_f64(words[8], "Scale")?;
+          // the relative path is counted from the folder of the objects file
+          let mesh_path = Path::new(file_path)
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(words[4]);
+          let mesh = Mesh::parse_from_file(&mesh_path.to_string_lossy(), position, scale)?;
+          objects.push(Objects::Mesh {
+            color: parse_color(&words[0..3])?,
+            material,
+            mesh: Arc::new(mesh),
+          });
+        }
+        _ => {
+          return Err(format!("Unknown command: {}", line));
+        }
+      }
     }
-}
\ No newline at end of file
+
+    Ok(objects)
+  }
+}
+
+/// the number of the object line, f.e. the radius, the name is for the error message
+fn parse_f64(word: &str, name: &str) -> Result<f64, String> {
+  word.parse::<f64>().map_err(|_| format!("{} must be a number", name))
+}
+
+/// the x y z numbers of the object line, f.e. parse_f64s(&words[4..7], "Position")
+fn parse_f64s(words: &[&str], name: &str) -> Result<[f64; 3], String> {
+  Ok([
+    parse_f64(words[0], &format!("{} x", name))?,
+    parse_f64(words[1], &format!("{} y", name))?,
+    parse_f64(words[2], &format!("{} z", name))?,
+  ])
+}
+
+/// the r g b color at the start of the object line
+fn parse_color(words: &[&str]) -> Result<[u8; 3], String> {
+  let parse = |word: &str, name: &str| {
+    word.parse::<u8>().map_err(|_| format!("Color {} must be an integer 0 to 255", name))
+  };
+  Ok([parse(words[0], "R")?, parse(words[1], "G")?, parse(words[2], "B")?])
+}
+
+/// the optional axis after the two sizes of the roll, cone and torus, vertical if not specified
+fn parse_axis(words: &[&str]) -> Result<Spear, String> {
+  if words.len() == 12 {
+    Ok(Spear::from_array(parse_f64s(&words[9..12], "Axis")?))
+  } else {
+    Ok(Spear::oy())
+  }
+}
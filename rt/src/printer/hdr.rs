@@ -0,0 +1,91 @@
+use std::error::Error;
+use std::fs::{File, create_dir_all};
+use std::io::{BufWriter, Write};
+use std::path::MAIN_SEPARATOR;
+
+// Define a function to generate a Radiance RGBE file from the radiance(not tone mapped)
+pub fn generate_hdr_file(width: u32, height: u32, file_name: &String, radiance: &[f64]) -> Result<(), Box<dyn Error>> {
+  // Create "ppm" directory if it doesn't exist, the same one for all the pictures
+  let dir_path = "ppm";
+  create_dir_all(dir_path)?;
+
+  let file_path = format!("{}{}{}", dir_path, MAIN_SEPARATOR, file_name);
+  let mut file = BufWriter::new(File::create(&file_path)?);
+  encode_hdr(&mut file, width, height, radiance)?;
+  file.flush()?;
+  Ok(())
+}
+
+/// the whole Radiance picture: the header, the size and the RGBE pixels
+fn encode_hdr(file: &mut impl Write, width: u32, height: u32, radiance: &[f64]) -> Result<(), Box<dyn Error>> {
+  // the header ends with the empty line, then the size, the rows go from the top, the columns from the left
+  writeln!(file, "#?RADIANCE")?;
+  writeln!(file, "FORMAT=32-bit_rle_rgbe")?;
+  writeln!(file)?;
+  writeln!(file, "-Y {} +X {}", height, width)?;
+
+  // the flat(not run length encoded) rows, four bytes per pixel
+  for pixel in radiance[..(width * height * 3) as usize].chunks_exact(3) {
+    file.write_all(&rgbe(pixel[0], pixel[1], pixel[2]))?;
+  }
+  Ok(())
+}
+
+/// the color as the three mantissas and the common exponent(plus 128) of the brightest component
+fn rgbe(r: f64, g: f64, b: f64) -> [u8; 4] {
+  let v = r.max(g).max(b);
+  if v < 1e-32 {
+    return [0; 4];
+  }
+  // v = m * 2^e, with m from 0.5 to 1
+  let mut e = v.log2().floor() as i32 + 1;
+  if v / 2f64.powi(e) >= 1.0 {
+    e += 1;
+  }
+  let scale = 256.0 / 2f64.powi(e);
+  let mantissa = |c: f64| (c.max(0.0) * scale).min(255.0) as u8;
+  [mantissa(r), mantissa(g), mantissa(b), (e + 128).clamp(0, 255) as u8]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// the color back from the RGBE bytes, the way the readers decode it(the middle of the mantissa step)
+  fn decode(rgbe: [u8; 4]) -> [f64; 3] {
+    if rgbe[3] == 0 {
+      return [0.0; 3];
+    }
+    let scale = 2f64.powi(rgbe[3] as i32 - 128 - 8);
+    [0, 1, 2].map(|i| (rgbe[i] as f64 + 0.5) * scale)
+  }
+
+  #[test]
+  fn rgbe_exponent_of_brightest_component() {
+    // 1 is 0.5 * 2^1, so the mantissa is 128 and the exponent is 1 + 128
+    assert_eq!(rgbe(1.0, 0.5, 0.0), [128, 64, 0, 129]);
+    assert_eq!(rgbe(0.0, 0.0, 0.0), [0; 4]);
+    assert_eq!(rgbe(-1.0, 0.0, 0.0), [0; 4]);
+  }
+
+  #[test]
+  fn rgbe_round_trip() {
+    for color in [[0.2, 0.4, 0.8], [1000.0, 1.0, 0.001], [0.999, 0.5, 0.25], [3.0e-5, 2.0e-5, 1.0e-5]] {
+      let decoded = decode(rgbe(color[0], color[1], color[2]));
+      let brightest = color.iter().cloned().fold(0.0, f64::max);
+      for (d, c) in decoded.iter().zip(color) {
+        // the mantissa keeps 8 bits of the brightest component
+        assert!((d - c).abs() <= brightest / 128.0, "{:?} decoded as {:?}", color, decoded);
+      }
+    }
+  }
+
+  #[test]
+  fn header_and_flat_pixels() {
+    let mut bytes = Vec::new();
+    encode_hdr(&mut bytes, 2, 1, &[1.0, 0.5, 0.0, 0.0, 0.0, 0.0]).unwrap();
+    let header = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n";
+    assert_eq!(&bytes[..header.len()], header);
+    assert_eq!(&bytes[header.len()..], &[128, 64, 0, 129, 0, 0, 0, 0]);
+  }
+}
@@ -0,0 +1,56 @@
+use std::error::Error;
+use std::fs::{File, create_dir_all};
+use std::io::{BufWriter, Write};
+use std::path::MAIN_SEPARATOR;
+
+// Define a function to generate a Portable Float Map file from the radiance(not tone mapped)
+pub fn generate_pfm_file(width: u32, height: u32, file_name: &String, radiance: &[f64]) -> Result<(), Box<dyn Error>> {
+  // Create "ppm" directory if it doesn't exist, the same one for all the pictures
+  let dir_path = "ppm";
+  create_dir_all(dir_path)?;
+
+  let file_path = format!("{}{}{}", dir_path, MAIN_SEPARATOR, file_name);
+  let mut file = BufWriter::new(File::create(&file_path)?);
+  encode_pfm(&mut file, width, height, radiance)?;
+  file.flush()?;
+  Ok(())
+}
+
+/// the whole PFM picture: the header and the f32 components
+fn encode_pfm(file: &mut impl Write, width: u32, height: u32, radiance: &[f64]) -> Result<(), Box<dyn Error>> {
+  // color picture, the negative scale means the little endian numbers
+  writeln!(file, "PF")?;
+  writeln!(file, "{} {}", width, height)?;
+  writeln!(file, "-1.0")?;
+
+  // the rows go from the bottom to the top
+  let stride = (width * 3) as usize;
+  for row in radiance[..stride * height as usize].chunks_exact(stride).rev() {
+    for &c in row {
+      file.write_all(&(c as f32).to_le_bytes())?;
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn header_and_bottom_row_first() {
+    // 1x2 picture, the top pixel is 1 2 3, the bottom one is 0.5 -1 1e6
+    let mut bytes = Vec::new();
+    encode_pfm(&mut bytes, 1, 2, &[1.0, 2.0, 3.0, 0.5, -1.0, 1e6]).unwrap();
+
+    let header = b"PF\n1 2\n-1.0\n";
+    assert_eq!(&bytes[..header.len()], header);
+    let values: Vec<f32> = bytes[header.len()..]
+      .chunks_exact(4)
+      .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+      .collect();
+    assert_eq!(values, [0.5, -1.0, 1e6, 1.0, 2.0, 3.0]);
+    // little endian, the lowest byte first
+    assert_eq!(&bytes[header.len() + 12..header.len() + 16], &[0x00, 0x00, 0x80, 0x3F]);
+  }
+}
@@ -0,0 +1,435 @@
+use std::error::Error;
+use std::fs::{File, create_dir_all};
+use std::io::{BufWriter, Write};
+use std::path::MAIN_SEPARATOR;
+
+use super::tone::to_u8;
+
+/// the first bytes of any PNG file
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+/// the deflate window, the repeated bytes are searched so far back
+const WINDOW: usize = 32768;
+/// the shortest and the longest repeat, encoded as the length and the distance
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+/// number of the previous positions with the same first bytes, checked to find the longest repeat
+const MAX_CHAIN: usize = 64;
+const HASH_BITS: u32 = 15;
+
+/// the smallest length of each length code 257..285, and the number of the extra bits after the code
+const LENGTH_BASE: [u16; 29] = [
+  3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+/// the smallest distance of each distance code 0..29, and the number of the extra bits after the code
+const DISTANCE_BASE: [u16; 30] = [
+  1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+  8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+// Define a function to generate a PNG file(8 bits RGB) from image data(the tone mapped components, from 0 to 1)
+pub fn generate_png_file(width: u32, height: u32, file_name: &String, data: &[f64]) -> Result<(), Box<dyn Error>> {
+  // Create "ppm" directory if it doesn't exist, the same one for all the pictures
+  let dir_path = "ppm";
+  create_dir_all(dir_path)?;
+
+  let file_path = format!("{}{}{}", dir_path, MAIN_SEPARATOR, file_name);
+  let mut file = BufWriter::new(File::create(&file_path)?);
+  encode_png(&mut file, width, height, data)?;
+  file.flush()?;
+  Ok(())
+}
+
+/// the whole PNG picture: the signature, the header, the compressed pixels and the end
+fn encode_png(file: &mut impl Write, width: u32, height: u32, data: &[f64]) -> Result<(), Box<dyn Error>> {
+  file.write_all(&SIGNATURE)?;
+
+  // width, height, 8 bits per component, truecolor(RGB), deflate, adaptive filters, no interlace
+  let mut header = Vec::with_capacity(13);
+  header.extend_from_slice(&width.to_be_bytes());
+  header.extend_from_slice(&height.to_be_bytes());
+  header.extend_from_slice(&[8, 2, 0, 0, 0]);
+  write_chunk(file, b"IHDR", &header)?;
+
+  let data: Vec<u8> = data.iter().map(|&c| to_u8(c)).collect();
+  let filtered = filter_rows(width as usize, height as usize, &data);
+  write_chunk(file, b"IDAT", &zlib(&filtered))?;
+  write_chunk(file, b"IEND", &[])?;
+  Ok(())
+}
+
+/// the chunk is the length, the type, the data and the CRC of the type and the data
+fn write_chunk(file: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> Result<(), Box<dyn Error>> {
+  file.write_all(&(data.len() as u32).to_be_bytes())?;
+  file.write_all(kind)?;
+  file.write_all(data)?;
+  file.write_all(&crc32(&[kind, data]).to_be_bytes())?;
+  Ok(())
+}
+
+/// the CRC-32 of the parts, one after another
+fn crc32(parts: &[&[u8]]) -> u32 {
+  let mut table = [0u32; 256];
+  for (n, entry) in table.iter_mut().enumerate() {
+    let mut c = n as u32;
+    for _ in 0..8 {
+      c = if c & 1 == 1 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+    }
+    *entry = c;
+  }
+
+  let mut crc = 0xFFFF_FFFF;
+  for &byte in parts.iter().flat_map(|part| part.iter()) {
+    crc = table[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+  }
+  crc ^ 0xFFFF_FFFF
+}
+
+/// the rows of the pixels, each one after the filter type byte.
+///
+/// Each row uses the filter, which gives the smallest sum of the differences,
+/// the small values are compressed better
+fn filter_rows(width: usize, height: usize, data: &[u8]) -> Vec<u8> {
+  let stride = width * 3;
+  let mut filtered = Vec::with_capacity((stride + 1) * height);
+  let zero_row = vec![0u8; stride];
+  let mut candidate = vec![0u8; stride];
+  let mut best = vec![0u8; stride];
+
+  for row in 0..height {
+    let current = &data[row * stride..(row + 1) * stride];
+    let above = if row == 0 { &zero_row[..] } else { &data[(row - 1) * stride..row * stride] };
+
+    let mut best_filter = 0;
+    let mut best_sum = u64::MAX;
+    for filter in 0..5u8 {
+      for i in 0..stride {
+        let left = if i >= 3 { current[i - 3] } else { 0 };
+        let up = above[i];
+        let up_left = if i >= 3 { above[i - 3] } else { 0 };
+        let predicted = match filter {
+          0 => 0,
+          1 => left,
+          2 => up,
+          3 => ((left as u16 + up as u16) / 2) as u8,
+          _ => paeth(left, up, up_left),
+        };
+        candidate[i] = current[i].wrapping_sub(predicted);
+      }
+      // the differences are signed, so the small negative ones are counted as small too
+      let sum: u64 = candidate.iter().map(|&c| (c as i8).unsigned_abs() as u64).sum();
+      if sum < best_sum {
+        best_sum = sum;
+        best_filter = filter;
+        best.copy_from_slice(&candidate);
+      }
+    }
+    filtered.push(best_filter);
+    filtered.extend_from_slice(&best);
+  }
+  filtered
+}
+
+/// the nearest to the left + up - up_left of the three neighbours
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+  let p = left as i16 + up as i16 - up_left as i16;
+  let pa = (p - left as i16).abs();
+  let pb = (p - up as i16).abs();
+  let pc = (p - up_left as i16).abs();
+  if pa <= pb && pa <= pc {
+    left
+  } else if pb <= pc {
+    up
+  } else {
+    up_left
+  }
+}
+
+/// the zlib stream: the header, the deflate data, and the Adler-32 of the uncompressed data
+fn zlib(data: &[u8]) -> Vec<u8> {
+  // deflate with 32K window, the default compression, the header is divisible by 31
+  let mut stream = vec![0x78, 0x9C];
+  stream.extend(deflate(data));
+
+  let (mut a, mut b) = (1u32, 0u32);
+  for &byte in data {
+    a = (a + byte as u32) % 65521;
+    b = (b + a) % 65521;
+  }
+  stream.extend_from_slice(&((b << 16) | a).to_be_bytes());
+  stream
+}
+
+/// the bits are written from the lowest one of each byte
+struct BitWriter {
+  bytes: Vec<u8>,
+  bit_buffer: u32,
+  bit_count: u32,
+}
+
+impl BitWriter {
+  fn new() -> BitWriter {
+    BitWriter { bytes: Vec::new(), bit_buffer: 0, bit_count: 0 }
+  }
+
+  /// the lowest count bits of the value, the lowest bit first
+  fn bits(&mut self, value: u32, count: u32) {
+    self.bit_buffer |= value << self.bit_count;
+    self.bit_count += count;
+    while self.bit_count >= 8 {
+      self.bytes.push(self.bit_buffer as u8);
+      self.bit_buffer >>= 8;
+      self.bit_count -= 8;
+    }
+  }
+
+  /// the Huffman code, the highest bit first
+  fn code(&mut self, code: u32, length: u32) {
+    let mut reversed = 0;
+    for i in 0..length {
+      reversed |= ((code >> i) & 1) << (length - 1 - i);
+    }
+    self.bits(reversed, length);
+  }
+
+  /// the literal byte, the length code or the end of the block, with the fixed Huffman codes
+  fn literal(&mut self, symbol: u32) {
+    match symbol {
+      0..=143 => self.code(0x30 + symbol, 8),
+      144..=255 => self.code(0x190 + symbol - 144, 9),
+      256..=279 => self.code(symbol - 256, 7),
+      _ => self.code(0xC0 + symbol - 280, 8),
+    }
+  }
+
+  fn finish(mut self) -> Vec<u8> {
+    if self.bit_count > 0 {
+      self.bytes.push(self.bit_buffer as u8);
+    }
+    self.bytes
+  }
+}
+
+/// one deflate block with the fixed Huffman codes.
+///
+/// The repeated bytes are replaced by the length and the distance back to the same bytes,
+/// found by the hash of the first three bytes
+fn deflate(data: &[u8]) -> Vec<u8> {
+  let mut out = BitWriter::new();
+  // the last block, the fixed Huffman codes
+  out.bits(1, 1);
+  out.bits(1, 2);
+
+  let hash = |i: usize| {
+    let v = (data[i] as u32) << 16 | (data[i + 1] as u32) << 8 | data[i + 2] as u32;
+    (v.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+  };
+  // the last position of each hash, and the previous position with the same hash for each position
+  let mut head = vec![usize::MAX; 1 << HASH_BITS];
+  let mut previous = vec![usize::MAX; WINDOW];
+  let insert = |i: usize, head: &mut [usize], previous: &mut [usize]| {
+    if i + MIN_MATCH <= data.len() {
+      let h = hash(i);
+      previous[i % WINDOW] = head[h];
+      head[h] = i;
+    }
+  };
+
+  let mut i = 0;
+  while i < data.len() {
+    let mut best_length = 0;
+    let mut best_distance = 0;
+    if i + MIN_MATCH <= data.len() {
+      let max_length = MAX_MATCH.min(data.len() - i);
+      let mut candidate = head[hash(i)];
+      let mut chain = 0;
+      while candidate != usize::MAX && i - candidate <= WINDOW && chain < MAX_CHAIN {
+        let length = (0..max_length).take_while(|&k| data[candidate + k] == data[i + k]).count();
+        if length > best_length {
+          best_length = length;
+          best_distance = i - candidate;
+          if length == max_length { break }
+        }
+        let next = previous[candidate % WINDOW];
+        // the older positions only, the slot can be reused by the newer one
+        if next == usize::MAX || next >= candidate { break }
+        candidate = next;
+        chain += 1;
+      }
+    }
+
+    if best_length >= MIN_MATCH {
+      let code = LENGTH_BASE.iter().rposition(|&base| base as usize <= best_length).unwrap_or(0);
+      out.literal(257 + code as u32);
+      out.bits((best_length - LENGTH_BASE[code] as usize) as u32, LENGTH_EXTRA[code] as u32);
+      let code = DISTANCE_BASE.iter().rposition(|&base| base as usize <= best_distance).unwrap_or(0);
+      out.code(code as u32, 5);
+      out.bits((best_distance - DISTANCE_BASE[code] as usize) as u32, DISTANCE_EXTRA[code] as u32);
+      for k in i..i + best_length {
+        insert(k, &mut head, &mut previous);
+      }
+      i += best_length;
+    } else {
+      out.literal(data[i] as u32);
+      insert(i, &mut head, &mut previous);
+      i += 1;
+    }
+  }
+
+  // the end of the block
+  out.literal(256);
+  out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// the bits of the deflate stream, from the lowest one of each byte
+  struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+  }
+
+  impl BitReader<'_> {
+    fn bit(&mut self) -> u32 {
+      let bit = (self.bytes[self.position / 8] >> (self.position % 8)) & 1;
+      self.position += 1;
+      bit as u32
+    }
+
+    /// the number, the lowest bit first
+    fn bits(&mut self, count: u32) -> u32 {
+      (0..count).fold(0, |value, i| value | self.bit() << i)
+    }
+
+    /// the Huffman code, the highest bit first
+    fn code(&mut self, length: u32) -> u32 {
+      (0..length).fold(0, |code, _| code << 1 | self.bit())
+    }
+
+    /// the symbol of the fixed Huffman literal/length codes
+    fn literal(&mut self) -> u32 {
+      let code = self.code(7);
+      if code <= 0x17 {
+        return 256 + code;
+      }
+      let code = code << 1 | self.bit();
+      match code {
+        0x30..=0xBF => code - 0x30,
+        0xC0..=0xC7 => 280 + code - 0xC0,
+        _ => 144 + (code << 1 | self.bit()) - 0x190,
+      }
+    }
+  }
+
+  /// the uncompressed data of the zlib stream, the blocks with the fixed Huffman codes only
+  fn inflate(stream: &[u8]) -> Vec<u8> {
+    assert_eq!((stream[0] as u16 * 256 + stream[1] as u16) % 31, 0, "zlib header check");
+    let mut reader = BitReader { bytes: &stream[2..], position: 0 };
+    let mut out: Vec<u8> = Vec::new();
+    loop {
+      let last = reader.bits(1);
+      assert_eq!(reader.bits(2), 1, "fixed Huffman block expected");
+      loop {
+        let symbol = reader.literal();
+        match symbol {
+          0..=255 => out.push(symbol as u8),
+          256 => break,
+          _ => {
+            let code = (symbol - 257) as usize;
+            let length = LENGTH_BASE[code] as usize + reader.bits(LENGTH_EXTRA[code] as u32) as usize;
+            let code = reader.code(5) as usize;
+            let distance = DISTANCE_BASE[code] as usize + reader.bits(DISTANCE_EXTRA[code] as u32) as usize;
+            for _ in 0..length {
+              out.push(out[out.len() - distance]);
+            }
+          }
+        }
+      }
+      if last == 1 { break }
+    }
+
+    let adler = &stream[stream.len() - 4..];
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in &out {
+      a = (a + byte as u32) % 65521;
+      b = (b + a) % 65521;
+    }
+    assert_eq!(adler, ((b << 16) | a).to_be_bytes(), "Adler-32 of the data");
+    out
+  }
+
+  /// the pixels of the filtered rows, each one after the filter type byte
+  fn unfilter(width: usize, height: usize, filtered: &[u8]) -> Vec<u8> {
+    let stride = width * 3;
+    assert_eq!(filtered.len(), (stride + 1) * height);
+    let mut data = vec![0u8; stride * height];
+    for row in 0..height {
+      let filter = filtered[row * (stride + 1)];
+      for i in 0..stride {
+        let left = if i >= 3 { data[row * stride + i - 3] } else { 0 };
+        let up = if row > 0 { data[(row - 1) * stride + i] } else { 0 };
+        let up_left = if row > 0 && i >= 3 { data[(row - 1) * stride + i - 3] } else { 0 };
+        let predicted = match filter {
+          0 => 0,
+          1 => left,
+          2 => up,
+          3 => ((left as u16 + up as u16) / 2) as u8,
+          4 => paeth(left, up, up_left),
+          _ => panic!("unknown filter {}", filter),
+        };
+        data[row * stride + i] = filtered[row * (stride + 1) + 1 + i].wrapping_add(predicted);
+      }
+    }
+    data
+  }
+
+  #[test]
+  fn decoded_pixels_are_the_same() {
+    let (width, height) = (13usize, 9usize);
+    // the gradients, the flat parts and the noise, so the rows use the different filters and the repeats
+    let mut data = Vec::with_capacity(width * height * 3);
+    for row in 0..height {
+      for col in 0..width {
+        let noise = ((row * 31 + col * 17) * 2654435761usize % 251) as f64 / 255.0;
+        data.push(col as f64 / width as f64);
+        data.push(if row < 3 { 0.5 } else { row as f64 / height as f64 });
+        data.push(if row % 2 == 0 { noise } else { 1.5 });
+      }
+    }
+
+    let mut png = Vec::new();
+    encode_png(&mut png, width as u32, height as u32, &data).unwrap();
+    assert_eq!(png[..8], SIGNATURE);
+
+    // the chunks, each one with the correct CRC
+    let mut chunks = Vec::new();
+    let mut position = 8;
+    while position < png.len() {
+      let length = u32::from_be_bytes(png[position..position + 4].try_into().unwrap()) as usize;
+      let kind = &png[position + 4..position + 8];
+      let body = &png[position + 8..position + 8 + length];
+      let crc = u32::from_be_bytes(png[position + 8 + length..position + 12 + length].try_into().unwrap());
+      assert_eq!(crc, crc32(&[kind, body]), "CRC of the {} chunk", String::from_utf8_lossy(kind));
+      chunks.push((kind.to_vec(), body.to_vec()));
+      position += 12 + length;
+    }
+    let kinds: Vec<&[u8]> = chunks.iter().map(|(kind, _)| &kind[..]).collect();
+    assert_eq!(kinds, [&b"IHDR"[..], b"IDAT", b"IEND"]);
+
+    let header = &chunks[0].1;
+    assert_eq!(header[..4], (width as u32).to_be_bytes());
+    assert_eq!(header[4..8], (height as u32).to_be_bytes());
+    assert_eq!(header[8..], [8, 2, 0, 0, 0]);
+
+    let filtered = inflate(&chunks[1].1);
+    let filters: std::collections::HashSet<u8> = filtered.iter().step_by(width * 3 + 1).copied().collect();
+    assert!(filters.len() > 1, "the rows use different filters: {:?}", filters);
+    let pixels = unfilter(width, height, &filtered);
+    let expected: Vec<u8> = data.iter().map(|&c| to_u8(c)).collect();
+    assert_eq!(pixels, expected);
+  }
+}
@@ -1,33 +1,49 @@
 use std::error::Error;
 use std::fs::{File, create_dir_all};
-use std::io::Write;
+use std::io::{BufWriter, Write};
 use std::path::MAIN_SEPARATOR;
 
-// Define a function to generate a PPM file from image data
-pub fn generate_ppm_file(width: u32, height: u32, file_name: &String, data: &[u8]) -> Result<(), Box<dyn Error>> {
+use crate::parser::camera_file::PpmFormat;
+
+use super::tone::{to_u16, to_u8};
+
+// Define a function to generate a PPM file from image data(the tone mapped components, from 0 to 1)
+pub fn generate_ppm_file(width: u32, height: u32, file_name: &String, data: &[f64], format: PpmFormat) -> Result<(), Box<dyn Error>> {
   // Create "ppm" directory if it doesn't exist
   let dir_path = format!("ppm");
   create_dir_all(&dir_path)?;
   
-  // Create PPM file
+  // Create PPM file, the buffer collects the small writes to the big ones
   let file_path = format!("{}{}{}", dir_path, MAIN_SEPARATOR, file_name);
-  let mut file = File::create(&file_path)?;
+  let mut file = BufWriter::new(File::create(&file_path)?);
   
   // Write PPM header
-  writeln!(file, "P3")?;
+  let (magic, maxval) = match format {
+    PpmFormat::P3 => ("P3", 255),
+    PpmFormat::P6 => ("P6", 255),
+    PpmFormat::P6Wide => ("P6", 65535),
+  };
+  writeln!(file, "{}", magic)?;
   writeln!(file, "{} {}", width, height)?;
-  writeln!(file, "255")?;
+  writeln!(file, "{}", maxval)?;
   
   // Write image data
-  for row in 0..height {
-    for col in 0..width {
-      let index = ((row * width + col) * 3) as usize;
-      let r = data[index];
-      let g = data[index + 1];
-      let b = data[index + 2];
-      writeln!(file, "{} {} {}", r, g, b)?;
+  let data = &data[..(width * height * 3) as usize];
+  match format {
+    PpmFormat::P3 => {
+      for pixel in data.chunks_exact(3) {
+        writeln!(file, "{} {} {}", to_u8(pixel[0]), to_u8(pixel[1]), to_u8(pixel[2]))?;
+      }
+    }
+    PpmFormat::P6 => file.write_all(&data.iter().map(|&c| to_u8(c)).collect::<Vec<u8>>())?,
+    // two bytes per component, the most significant first
+    PpmFormat::P6Wide => {
+      for &c in data {
+        file.write_all(&to_u16(c).to_be_bytes())?;
+      }
     }
   }
   
+  file.flush()?;
   Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,109 @@
+use crate::parser::camera_file::{Camera, ToneMap};
+
+/// the picture brightness(0 to 1) of the radiance components, three per pixel.
+///
+/// The radiance is multiplied by 2 to the power of the camera exposure, compressed by the tone map,
+/// and encoded by the camera gamma, or by the sRGB curve without the gamma
+pub fn tone_map(radiance: &[f64], camera: &Camera) -> Vec<f64> {
+  let scale = 2f64.powf(camera.exposure);
+  radiance
+    .iter()
+    .map(|&x| {
+      let x = (x * scale).max(0.0);
+      let mapped = match camera.tone {
+        ToneMap::Clamp => x,
+        ToneMap::Reinhard => x / (1.0 + x),
+        // the curve fitted to the ACES film response by Krzysztof Narkowicz
+        ToneMap::Filmic => (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14),
+      };
+      match camera.gamma {
+        Some(gamma) => mapped.min(1.0).powf(1.0 / gamma),
+        None => linear_to_srgb(mapped.min(1.0)),
+      }
+    })
+    .collect()
+}
+
+/// the light intensity(0 to 1) of the sRGB encoded component(0 to 1), the colors of the scene files are sRGB
+pub fn srgb_to_linear(c: f64) -> f64 {
+  if c <= 0.04045 {
+    c / 12.92
+  } else {
+    ((c + 0.055) / 1.055).powf(2.4)
+  }
+}
+
+/// the sRGB encoded component(0 to 1) of the light intensity(0 to 1), the way the screens show it
+pub fn linear_to_srgb(l: f64) -> f64 {
+  if l <= 0.0031308 {
+    l * 12.92
+  } else {
+    1.055 * l.powf(1.0 / 2.4) - 0.055
+  }
+}
+
+/// the picture brightness(0 to 1) as the 0-255 component
+pub fn to_u8(value: f64) -> u8 {
+  (value * 255.0).round() as u8
+}
+
+/// the picture brightness(0 to 1) as the 0-65535 component
+pub fn to_u16(value: f64) -> u16 {
+  (value * 65535.0).round() as u16
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::camera_file::{PpmFormat, Projection, SamplePattern};
+
+  /// the camera with the tone mapping settings, the other ones are not used
+  fn camera(tone: ToneMap, exposure: f64, gamma: Option<f64>) -> Camera {
+    Camera {
+      width: 1, height: 1, output_file_name: String::new(), zoom: 1, fov: 0.0, position: [0.0; 3],
+      look_at: [0.0; 3], up: [0.0; 3], roll: 0.0, depth: 0, samples: 1, pattern: SamplePattern::Grid,
+      adaptive: 0, aperture: 0.0, focus: 1.0, projection: Projection::Perspective, format: PpmFormat::P3,
+      tone, exposure, gamma, passes: Vec::new(),
+    }
+  }
+
+  fn assert_close(found: Vec<f64>, expected: &[f64]) {
+    assert!(found.iter().zip(expected).all(|(f, e)| (f - e).abs() < 1e-6), "{:?}, expected {:?}", found, expected);
+  }
+
+  #[test]
+  fn srgb_round_trip() {
+    for c in [0.0, 0.001, 0.04045, 0.2, 0.5, 1.0] {
+      assert!((linear_to_srgb(srgb_to_linear(c)) - c).abs() < 1e-6, "{}", c);
+    }
+    // the middle gray of the screen is about one fifth of the light
+    assert!((srgb_to_linear(0.5) - 0.214).abs() < 1e-3);
+  }
+
+  #[test]
+  fn operators() {
+    let radiance = [0.0, 0.5, 1.0, 4.0, -1.0];
+    let gamma_one = Some(1.0);
+    assert_close(tone_map(&radiance, &camera(ToneMap::Clamp, 0.0, gamma_one)), &[0.0, 0.5, 1.0, 1.0, 0.0]);
+    assert_close(tone_map(&radiance, &camera(ToneMap::Reinhard, 0.0, gamma_one)), &[0.0, 1.0 / 3.0, 0.5, 0.8, 0.0]);
+    // the filmic curve starts from the black, and reaches the white a bit over 10
+    let filmic = tone_map(&[0.0, 0.18, 10.0, 100.0], &camera(ToneMap::Filmic, 0.0, gamma_one));
+    assert_close(vec![filmic[0], filmic[3]], &[0.0, 1.0]);
+    assert!(filmic[1] > 0.2 && filmic[1] < 0.3 && filmic[2] > 0.99, "{:?}", filmic);
+  }
+
+  #[test]
+  fn exposure_and_encoding() {
+    // one stop doubles the radiance
+    assert_close(tone_map(&[0.25], &camera(ToneMap::Clamp, 1.0, Some(1.0))), &[0.5]);
+    assert_close(tone_map(&[0.25], &camera(ToneMap::Clamp, 0.0, Some(2.0))), &[0.5]);
+    // without the gamma, the sRGB curve
+    assert_close(tone_map(&[srgb_to_linear(0.5)], &camera(ToneMap::Clamp, 0.0, None)), &[0.5]);
+  }
+
+  #[test]
+  fn components() {
+    assert_eq!((to_u8(0.0), to_u8(0.5), to_u8(1.0)), (0, 128, 255));
+    assert_eq!((to_u16(0.0), to_u16(1.0)), (0, 65535));
+  }
+}
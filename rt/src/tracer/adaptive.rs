@@ -0,0 +1,104 @@
+use crate::{
+  gem::bvh::Bvh,
+  parser::objects_file::Objects,
+  tracer::{scene::Scene, pixel_::{RGB, PassHit}}
+};
+
+use crate::printer::tone::linear_to_srgb;
+
+use rayon::prelude::*;
+
+/// the biggest difference of the sRGB encoded color components(1 is 255) of the neighbour rays, which is not the edge
+const EDGE_THRESHOLD: f64 = 16.0 / 255.0;
+
+/// the color of the ray, and the nearest hit of it, None if nothing is hit
+type Sample = (RGB, Option<PassHit>);
+
+impl Scene {
+
+  /// the pixels colors, rendered with one ray through each pixel first.
+  ///
+  /// The pixels on the edges(the color or the object differs from the neighbour pixel)
+  /// are split to four parts, with the ray through each one,
+  /// and the parts on the edges are split again, up to the camera adaptive number of times.
+  /// The colors of the parts are averaged, the hit is the one of the ray through the pixel center
+  pub fn trace_adaptive(&self, good_to_trace: &[Objects], bvh: &Bvh) -> Vec<Sample> {
+    let width = self.camera.width;
+    let height = self.camera.height;
+
+    let first: Vec<Sample> = (0..(width * height))
+      .into_par_iter()
+      .map(|i| self.sample((i / width) as f64, (i % width) as f64, good_to_trace, bvh))
+      .collect();
+
+    (0..(width * height))
+      .into_par_iter()
+      .map(|i| {
+        let row = i / width;
+        let col = i % width;
+        let mut neighbours = Vec::with_capacity(4);
+        if row > 0 { neighbours.push(i - width) }
+        if row + 1 < height { neighbours.push(i + width) }
+        if col > 0 { neighbours.push(i - 1) }
+        if col + 1 < width { neighbours.push(i + 1) }
+
+        let color = if neighbours.iter().any(|&n| Scene::is_edge(&first[i], &first[n])) {
+          self.refined(row as f64, col as f64, 1.0, 1, good_to_trace, bvh)
+        } else {
+          first[i].0.same()
+        };
+        (color, first[i].1)
+      })
+      .collect()
+  }
+
+  /// the color and the object of the ray through the point of the camera screen
+  fn sample(&self, row: f64, col: f64, good_to_trace: &[Objects], bvh: &Bvh) -> Sample {
+    let ray = self.camera_ray_through(row, col);
+    self.ray_hit_color(ray, self.camera.depth, good_to_trace, bvh)
+  }
+
+  /// check the rays hit the different objects, or the colors are too different.
+  ///
+  /// The colors brighter than 1 are compared as 1, the difference between them is not visible without the exposure.
+  /// The colors are compared sRGB encoded, the way the difference is seen on the screen
+  fn is_edge(a: &Sample, b: &Sample) -> bool {
+    let seen = |c: f64| linear_to_srgb(c.clamp(0.0, 1.0));
+    a.1.map(|h| h.hit.index) != b.1.map(|h| h.hit.index)
+      || a.0.to_array().iter().zip(b.0.to_array()).any(|(&a, b)| (seen(a) - seen(b)).abs() > EDGE_THRESHOLD)
+  }
+
+  /// the averaged color of the square of the camera screen, with the center in the row and column.
+  ///
+  /// The ray goes through the center of each quarter of the square,
+  /// if they are on the edge, and the level is not over, each quarter is refined the same way
+  fn refined(
+    &self,
+    row: f64,
+    col: f64,
+    size: f64,
+    level: usize,
+    good_to_trace: &[Objects],
+    bvh: &Bvh,
+  ) -> RGB {
+    let q = size / 4.0;
+    let quarters = [(-q, -q), (-q, q), (q, -q), (q, q)];
+    let samples: Vec<Sample> = quarters
+      .iter()
+      .map(|(dr, dc)| self.sample(row + dr, col + dc, good_to_trace, bvh))
+      .collect();
+
+    let split = level < self.camera.adaptive && samples[1..].iter().any(|s| Scene::is_edge(&samples[0], s));
+    let mut sum = RGB::black();
+    for ((dr, dc), sample) in quarters.iter().zip(&samples) {
+      let color = if split {
+        self.refined(row + dr, col + dc, size / 2.0, level + 1, good_to_trace, bvh)
+      } else {
+        sample.0.same()
+      };
+      sum = sum.plus(&color);
+    }
+    RGB::new(sum.r / 4.0, sum.g / 4.0, sum.b / 4.0)
+  }
+
+}
@@ -1,6 +1,9 @@
+use std::f64::consts::PI;
+
 use crate::gem::{spear::Spear, dot::{Dot, self}, mat::Mat};
+use crate::parser::camera_file::Projection;
 
-use super::scene::Scene;
+use super::{sample::Rng, scene::Scene};
 
 impl Scene {
 
@@ -34,7 +37,49 @@ impl Scene {
     // first, inside brackets, it calculates the left camera direction vecotor
     // second, outside brackets, it recalculates the normal vector again
     // after that it is perpendicular to both camera vector and camera left vector
-    cv.normal(&cuv.normal(&cv))
+    let up = cv.normal(&cuv.normal(&cv));
+    if self.camera.roll == 0.0 { return up }
+
+    // turn the up vector clockwise around the camera vector, to the right vector
+    let right = cv.normal(&up);
+    let (sin, cos) = self.camera.roll.to_radians().sin_cos();
+    Spear::new(
+      up.x * cos + right.x * sin,
+      up.y * cos + right.y * sin,
+      up.z * cos + right.z * sin,
+    )
+  }
+
+  /// the descriptions of the camera orientation problems, fixed by the camera_vector and camera_up_vector.
+  ///
+  /// Empty if the camera file is fine
+  pub fn camera_orientation_warnings(&self) -> Vec<String> {
+    let camera = &self.camera;
+    let position = self.camera_position();
+    let mut warnings = Vec::new();
+
+    let v = Spear::pp(&[position, Dot::from_array(camera.look_at)]);
+    if v.is_zero() {
+      warnings.push(format!(
+        "the camera position [from] and the look at point [to] are the same {:?}, the camera looks along the z axis",
+        camera.position
+      ));
+    }
+
+    let cuv = Spear::pp(&[position, Dot::from_array(camera.up)]);
+    if cuv.is_zero() {
+      warnings.push(format!(
+        "the up vector end point [up] {:?} is the same as the camera position [from], the y axis is added to the up vector",
+        camera.up
+      ));
+    } else if cuv.is_ll(&self.camera_vector()) {
+      warnings.push(format!(
+        "the up vector from the camera position [from] to the [up] point {:?} is parallel to the camera direction, the y axis is added to the up vector",
+        camera.up
+      ));
+    }
+
+    warnings
   }
 
   /// vector of the left direction of the camera
@@ -187,6 +232,17 @@ impl Scene {
     dot.offset(&v, -(self.camera.zoom as f64))
   }
 
+  /// distance from the camera position to the center of the camera screen, along the camera vector.
+  ///
+  /// With the zoom, or not perspective projection, the screen is in the camera position.
+  /// With the fov the rays start from the camera position, and the screen is moved forward,
+  /// so its width(in pixels) is seen with the fov angle. The angle does not depend on the picture size,
+  /// so the picture of any size shows the same part of the scene
+  pub fn camera_screen_distance(&self) -> f64 {
+    if self.camera.fov <= 0.0 || self.camera.projection != Projection::Perspective { return 0.0 }
+    self.camera.width as f64 / 2.0 / (self.camera.fov.to_radians() / 2.0).tan()
+  }
+
   /// from this point x,y,z coordinates will be calculated displacement
   /// 
   /// for the rays (ray direction end point), from the zoom point
@@ -195,7 +251,7 @@ impl Scene {
   /// 
   /// to the pixel on the camera plane  
   pub fn camera_left_top_pixel(&self) -> Dot {
-    let mut camera_position = self.camera_position();
+    let mut camera_position = self.camera_position().offset(&self.camera_vector(), self.camera_screen_distance());
     let camera_left_vector = self.camera_left_vector();
     let camera_up_vector = self.camera_up_vector();
 
@@ -231,18 +287,35 @@ impl Scene {
   /// 
   /// to down and right, according to the row and column
   pub fn camera_ray_to_pixel(&self, row: usize, col: usize) -> Mat {
+    self.camera_ray_through(row as f64, col as f64)
+  }
+
+  /// ray from the camera zoom position to the point of the camera screen,
+  /// the row and column can be fractional, to hit the point inside the pixel.
+  /// 
+  /// The whole row and column are the pixel center
+  pub fn camera_ray_through(&self, row: f64, col: f64) -> Mat {
+    if self.camera.projection == Projection::Equirectangular {
+      return self.camera_panorama_ray(row, col);
+    }
+
     let camera_zoom_position = self.camera_zoom_position();
     let camera_left_top_pixel = self.camera_left_top_pixel();
     let camera_screen_pixel = camera_left_top_pixel
     .offset( // offset down
       &self.camera_up_vector().back(),
-      row as f64
+      row
     )
     .offset( // offset right
       &self.camera_left_vector().back(),
-      col as f64
+      col
     );
     
+    // the parallel rays start from the screen
+    if self.camera.projection == Projection::Orthographic {
+      return Mat::new(camera_screen_pixel, self.camera_vector());
+    }
+
     Mat::pp(
       camera_zoom_position,
       camera_screen_pixel,
@@ -250,4 +323,56 @@ impl Scene {
 
   }
 
+  /// ray from the camera position, for the equirectangular projection.
+  ///
+  /// The picture center is the camera vector, the columns are the angles from -180 to 180 degrees
+  /// around the up vector(to the right), and the rows are the angles from 90 to -90 degrees
+  /// from the camera vector to the up vector(to the down)
+  fn camera_panorama_ray(&self, row: f64, col: f64) -> Mat {
+    let longitude = ((col + 0.5) / self.camera.width as f64 - 0.5) * 2.0 * PI;
+    let latitude = (0.5 - (row + 0.5) / self.camera.height as f64) * PI;
+
+    let forward = self.camera_vector();
+    let right = self.camera_left_vector().back();
+    let up = self.camera_up_vector();
+    let along = |s: &Spear, k: f64| [s.x * k, s.y * k, s.z * k];
+    let f = along(&forward, latitude.cos() * longitude.cos());
+    let r = along(&right, latitude.cos() * longitude.sin());
+    let u = along(&up, latitude.sin());
+
+    Mat::new(
+      self.camera_position(),
+      Spear::new(f[0] + r[0] + u[0], f[1] + r[1] + u[1], f[2] + r[2] + u[2]),
+    )
+  }
+
+  /// ray through the point of the camera screen, from the random point of the lens
+  /// around the camera zoom position, perpendicular to the camera vector.
+  ///
+  /// All the rays through the point meet on the sharp plane at the focus distance from the camera position,
+  /// so the objects there are sharp, and the nearer or farther ones are blurred.
+  /// Without the aperture it is the same ray as camera_ray_through
+  pub fn camera_lens_ray(&self, row: f64, col: f64, rng: &mut Rng) -> Mat {
+    let pinhole_ray = self.camera_ray_through(row, col);
+    if self.camera.aperture <= 0.0 { return pinhole_ray }
+
+    // the point of the sharp plane, the pinhole ray goes through.
+    // The ray starts behind the camera position(perspective), or in it(orthographic)
+    let cv = self.camera_vector();
+    let camera_position = self.camera_position();
+    let behind = (camera_position.x - pinhole_ray.origin.x) * cv.x
+      + (camera_position.y - pinhole_ray.origin.y) * cv.y
+      + (camera_position.z - pinhole_ray.origin.z) * cv.z;
+    let distance = (behind + self.camera.focus) / pinhole_ray.normal.scalar(&cv);
+    let sharp = pinhole_ray.origin.offset(&pinhole_ray.normal, distance);
+
+    let (u, v) = rng.next_in_disk();
+    let radius = self.camera.aperture / 2.0;
+    let lens = pinhole_ray.origin
+    .offset(&self.camera_left_vector(), u * radius)
+    .offset(&self.camera_up_vector(), v * radius);
+
+    Mat::pp(lens, sharp)
+  }
+
 }
\ No newline at end of file
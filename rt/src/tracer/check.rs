@@ -6,17 +6,21 @@ use crate::{
 use super::scene::Scene;
 
 use rayon::prelude::*;
-use std::sync::Arc;
 
 impl Scene {
-    pub fn good_to_trace(&self, objects: &[Objects]) -> Vec<Arc<Objects>> {
-        let camera_front_plane = self.camera_front_plane();
-        let camera_left_plane = self.camera_left_plane();
-        let camera_right_plane = self.camera_right_plane();
-        let camera_top_plane = self.camera_top_plane();
-        let camera_bottom_plane = self.camera_bottom_plane();
-        let light_position = Dot::from_array(self.light.position);
-        let light_power = self.light.power;
+    /// the objects, which can be visible, and so are traced.
+    ///
+    /// The wrong ones(zero size, zero normal) and the ones too far from all the lights are dropped always.
+    /// With in_view_only, the ones out of the camera view(the planes around the perspective camera view) are dropped too
+    pub fn good_to_trace(&self, objects: &[Objects], in_view_only: bool) -> Vec<Objects> {
+        let view = in_view_only.then(|| [
+            self.camera_front_plane(),
+            self.camera_left_plane(),
+            self.camera_right_plane(),
+            self.camera_top_plane(),
+            self.camera_bottom_plane(),
+        ]);
+        let view = view.as_ref();
 
         objects
             .par_iter()
@@ -24,21 +28,38 @@ impl Scene {
                 let is_good = match object {
                     Objects::Mat {
                         position, normal, ..
-                    } => self.mat_is_good(position, normal),
+                    } => self.mat_is_good(position, normal, view),
                     Objects::Ball {
                         position, radius, ..
-                    } => self.ball_is_good(position, radius),
-                    Objects::Box { position, size, .. } => self.box_is_good(position, size),
+                    } => self.ball_is_good(position, radius, view),
+                    Objects::Box { position, size, .. } => self.box_is_good(position, size, view),
                     Objects::Roll {
                         position,
                         radius,
                         height,
                         ..
-                    } => self.roll_is_good(position, radius, height),
+                    } => self.roll_is_good(position, radius, height, view),
+                    Objects::Cone {
+                        position,
+                        radius,
+                        height,
+                        axis,
+                        ..
+                    } => self.cone_is_good(position, radius, height, axis, view),
+                    Objects::Disk {
+                        position, radius, ..
+                    } => self.disk_is_good(position, radius, view),
+                    Objects::Torus {
+                        position,
+                        major_radius,
+                        minor_radius,
+                        ..
+                    } => self.torus_is_good(position, major_radius, minor_radius, view),
+                    Objects::Mesh { mesh, .. } => self.mesh_is_good(&mesh.min, &mesh.max, view),
                 };
 
                 if is_good {
-                    Some(Arc::new(object.clone()))
+                    Some(object.clone())
                 } else {
                     None
                 }
@@ -46,84 +67,117 @@ impl Scene {
             .collect()
     }
 
-    fn mat_is_good(&self, position: &[f64; 3], normal: &[f64; 3]) -> bool {
+    fn mat_is_good(&self, position: &[f64; 3], normal: &[f64; 3], view: Option<&[Mat; 5]>) -> bool {
+        // if plane is zero, then ignore it, before the plane is built
+        if normal.iter().all(|n| *n == 0.0) {
+            return false;
+        }
         let p = Mat::new(Dot::from_array(*position), Spear::from_array(*normal));
 
-        let camera_front_plane = self.camera_front_plane();
-        let camera_left_plane = self.camera_left_plane();
-        let camera_right_plane = self.camera_right_plane();
-        let camera_top_plane = self.camera_top_plane();
-        let camera_bottom_plane = self.camera_bottom_plane();
-        // if plane is zero, then ignore it
-        // if plane is too far from the light, then ignore it
+        // if plane is too far from all the lights, then ignore it
         // if plane is below any camera planes, or the same as any camera plane, then ignore it
         !(
             // negotiation of the conditions, to return false, if any of them is true
             p.is_zero()
-                || Dot::from_array(self.light.position).d_mat(&p) > self.light.power
-                || p.is_ll(&camera_front_plane) && !p.origin.is_above(&camera_front_plane)
-                || p.is_ll(&camera_left_plane) && !p.origin.is_above(&camera_left_plane)
-                || p.is_ll(&camera_right_plane) && !p.origin.is_above(&camera_right_plane)
-                || p.is_ll(&camera_top_plane) && !p.origin.is_above(&camera_top_plane)
-                || p.is_ll(&camera_bottom_plane) && !p.origin.is_above(&camera_bottom_plane)
+                || self.lights.iter().all(|light| light.is_mat_too_far(&p))
+                || view.is_some_and(|view| {
+                    view.iter().any(|plane| p.is_ll(plane) && !p.origin.is_above(plane))
+                })
         )
     }
 
-    fn ball_is_good(&self, position: &[f64; 3], radius: &f64) -> bool {
-        let c = Dot::from_array(*position);
-        let sun = Dot::from_array(self.light.position);
-        let v_light = Spear::pp(&[c, sun]);
-        let nearest_dot = c.offset(&v_light, *radius);
-        let distance_to_sun = sun.d_dot(&nearest_dot);
+    /// check the ball around the object is farther than the power distance of each light
+    fn is_too_far_from_lights(&self, c: &Dot, radius: f64) -> bool {
+        self.lights.iter().all(|light| light.is_too_far(c, radius))
+    }
 
-        let camera_front_plane = self.camera_front_plane();
-        let camera_left_plane = self.camera_left_plane();
-        let camera_right_plane = self.camera_right_plane();
-        let camera_top_plane = self.camera_top_plane();
-        let camera_bottom_plane = self.camera_bottom_plane();
+    fn ball_is_good(&self, position: &[f64; 3], radius: &f64, view: Option<&[Mat; 5]>) -> bool {
+        let c = Dot::from_array(*position);
 
         !(
             // negotiation of the conditions, to return false, if any of them is true
             radius <= &0.0
-                || distance_to_sun > self.light.power
-                || c.d_mat(&camera_front_plane) < *radius
-                || c.d_mat(&camera_front_plane) >= *radius && c.is_below(&camera_front_plane)
-                || c.d_mat(&camera_left_plane) >= *radius && c.is_below(&camera_left_plane)
-                || c.d_mat(&camera_right_plane) >= *radius && c.is_below(&camera_right_plane)
-                || c.d_mat(&camera_top_plane) >= *radius && c.is_below(&camera_top_plane)
-                || c.d_mat(&camera_bottom_plane) >= *radius && c.is_below(&camera_bottom_plane)
+                || self.is_too_far_from_lights(&c, *radius)
+                || view.is_some_and(|view| Scene::is_out_of_view(&c, *radius, view))
         )
     }
 
-    fn box_is_good(&self, position: &[f64; 3], size: &f64) -> bool {
-        let c = Dot::from_array(*position);
-        let sun = Dot::from_array(self.light.position);
-        let v_light = Spear::pp(&[c, sun]);
-        let d = (3.0 * (size / 2.0).powi(2)).sqrt(); // distance from box center to box corner
-        let nearest_dot = c.offset(&v_light, d);
+    /// check the ball is out of the camera view, or crosses the camera front plane.
+    /// The view planes are front, left, right, top, bottom
+    fn is_out_of_view(c: &Dot, radius: f64, view: &[Mat; 5]) -> bool {
+        let [camera_front_plane, camera_left_plane, camera_right_plane, camera_top_plane, camera_bottom_plane] = view;
+        c.d_mat(camera_front_plane) < radius
+            || c.d_mat(camera_front_plane) >= radius && c.is_below(camera_front_plane)
+            || c.d_mat(camera_left_plane) >= radius && c.is_below(camera_left_plane)
+            || c.d_mat(camera_right_plane) >= radius && c.is_below(camera_right_plane)
+            || c.d_mat(camera_top_plane) >= radius && c.is_below(camera_top_plane)
+            || c.d_mat(camera_bottom_plane) >= radius && c.is_below(camera_bottom_plane)
+    }
 
-        let camera_front_plane = self.camera_front_plane();
-        let camera_left_plane = self.camera_left_plane();
-        let camera_right_plane = self.camera_right_plane();
-        let camera_top_plane = self.camera_top_plane();
-        let camera_bottom_plane = self.camera_bottom_plane();
+    /// the box can be rotated, so the distance from the box center to the box corner
+    /// (half of the box diagonal) is used, it does not depend on the box orientation
+    fn box_is_good(&self, position: &[f64; 3], size: &[f64; 3], view: Option<&[Mat; 5]>) -> bool {
+        let c = Dot::from_array(*position);
+        let d = (size[0].powi(2) + size[1].powi(2) + size[2].powi(2)).sqrt() / 2.0; // distance from box center to box corner
 
         !(
             // negotiation of the conditions, to return false, if any of them is true
-            size <= &0.0
-                || sun.d_dot(&nearest_dot) > self.light.power
-                || c.d_mat(&camera_front_plane) < d
-                || c.d_mat(&camera_front_plane) >= d && c.is_below(&camera_front_plane)
-                || c.d_mat(&camera_left_plane) >= d && c.is_below(&camera_left_plane)
-                || c.d_mat(&camera_right_plane) >= d && c.is_below(&camera_right_plane)
-                || c.d_mat(&camera_top_plane) >= d && c.is_below(&camera_top_plane)
-                || c.d_mat(&camera_bottom_plane) >= d && c.is_below(&camera_bottom_plane)
+            size.iter().any(|s| s <= &0.0)
+                || self.is_too_far_from_lights(&c, d)
+                || view.is_some_and(|view| Scene::is_out_of_view(&c, d, view))
         )
     }
 
-    /// just recall box_is_good() with the biggest size of the roll
-    fn roll_is_good(&self, position: &[f64; 3], radius: &f64, height: &f64) -> bool {
-        radius > &0.0 && height > &0.0 && self.box_is_good(position, &(radius * 2.0).max(*height))
+    /// just recall box_is_good() with the box around the roll.
+    /// box_is_good() uses half of the box diagonal, so the roll axis direction does not matter
+    fn roll_is_good(&self, position: &[f64; 3], radius: &f64, height: &f64, view: Option<&[Mat; 5]>) -> bool {
+        radius > &0.0 && height > &0.0 && self.box_is_good(position, &[radius * 2.0, *height, radius * 2.0], view)
+    }
+
+    /// just recall ball_is_good() with the ball around the cone.
+    /// The ball center is in the middle of the cone axis, so the base edge is the farthest
+    fn cone_is_good(
+        &self,
+        position: &[f64; 3],
+        radius: &f64,
+        height: &f64,
+        axis: &[f64; 3],
+        view: Option<&[Mat; 5]>,
+    ) -> bool {
+        let middle = Dot::from_array(*position).offset(&Spear::from_array(*axis), height / 2.0);
+        radius > &0.0
+            && height > &0.0
+            && self.ball_is_good(&middle.to_array(), &(radius.powi(2) + (height / 2.0).powi(2)).sqrt(), view)
+    }
+
+    /// just recall ball_is_good() with the ball around the disk
+    fn disk_is_good(&self, position: &[f64; 3], radius: &f64, view: Option<&[Mat; 5]>) -> bool {
+        self.ball_is_good(position, radius, view)
+    }
+
+    /// just recall ball_is_good() with the ball around the torus
+    fn torus_is_good(
+        &self,
+        position: &[f64; 3],
+        major_radius: &f64,
+        minor_radius: &f64,
+        view: Option<&[Mat; 5]>,
+    ) -> bool {
+        major_radius > &0.0
+            && minor_radius > &0.0
+            && self.ball_is_good(position, &(major_radius + minor_radius), view)
+    }
+
+    /// just recall ball_is_good() with the ball around the box around the mesh
+    fn mesh_is_good(&self, min: &[f64; 3], max: &[f64; 3], view: Option<&[Mat; 5]>) -> bool {
+        let min = Dot::from_array(*min);
+        let max = Dot::from_array(*max);
+        let center = Dot::new(
+            (min.x + max.x) / 2.0,
+            (min.y + max.y) / 2.0,
+            (min.z + max.z) / 2.0,
+        );
+        self.ball_is_good(&center.to_array(), &(min.d_dot(&max) / 2.0), view)
     }
 
     /// prints the bad objects, that are not good to trace
@@ -0,0 +1,63 @@
+use crate::{
+  gem::{bvh::Bvh, hit::Hit, mat::Mat},
+  parser::objects_file::Objects,
+  tracer::{scene::Scene, pixel_::RGB}
+};
+
+/// maximum number of the total internal reflections inside the object, before the ray leaves it
+const MAX_INSIDE_BOUNCES: usize = 8;
+
+impl Scene {
+
+  /// color of the light come through the transparent surface in the hit position.
+  ///
+  /// The ray is split to the reflected and the refracted(Snell's law) rays, which are traced
+  /// with the depth decreased, and mixed by the Fresnel reflectance.
+  /// If there is total internal reflection, only the reflected ray is used
+  pub fn glass_color(
+    &self,
+    ray: &Mat,
+    hit: &Hit,
+    object: &Objects,
+    depth: usize,
+    good_to_trace: &[Objects],
+    bvh: &Bvh,
+  ) -> RGB {
+    let material = object.material();
+    // the ray enters the object from outside, or leaves it from inside
+    let eta = if hit.front { 1.0 / material.ior } else { material.ior };
+
+    let reflected = self.ray_color(hit.reflected(ray), depth - 1, good_to_trace, bvh);
+    let Some((refracted, fresnel)) = hit.refracted(ray, eta) else { return reflected };
+
+    let refracted = if hit.front { Scene::pass_through(refracted, object, material.ior) } else { Some(refracted) };
+    let through = match refracted {
+      Some(refracted) => self.ray_color(refracted, depth - 1, good_to_trace, bvh).tinted(material.tint),
+      // the light is trapped inside the object
+      None => RGB::black(),
+    };
+
+    reflected.mix(&through, 1.0 - fresnel)
+  }
+
+  /// the ray leaving the object, which the refracted ray entered.
+  ///
+  /// The ball, box and roll are convex, so the ray exits through their far hit,
+  /// and it is found at once, without tracing of the other objects(they are not visible inside).
+  /// The light reflected back inside on the exit is lost, except the total internal reflection.
+  /// Other objects return the same ray, to trace it as usual, and leave them on the next hit.
+  /// None if the ray is trapped inside by the total internal reflections
+  fn pass_through(entered: Mat, object: &Objects, ior: f64) -> Option<Mat> {
+    let mut inside = entered;
+    for _ in 0..MAX_INSIDE_BOUNCES {
+      // the ray starts on the surface, so the far hit is the exit
+      let Some([_, exit]) = Scene::ray_x_object_both(&inside, object) else { return Some(inside) };
+      match exit.refracted(&inside, ior) {
+        Some((out, _)) => return Some(out),
+        None => inside = exit.reflected(&inside),
+      }
+    }
+    None
+  }
+
+}
@@ -0,0 +1,123 @@
+use crate::{
+  gem::{dot::Dot, mat::Mat, spear::Spear},
+  parser::light_file::{LampShape, Light, LightKind},
+};
+
+/// distance from the scene to the sun position, used for the rays to the sun.
+///
+/// Far enough to be out of any scene, and not too far to keep the intersections precision
+const SUN_DISTANCE: f64 = 1_000_000.0;
+
+/// fractional part of the golden ratio, the step of the even sequence of the samples
+const GOLDEN_RATIO: f64 = 0.618_033_988_749_895;
+
+impl Light {
+
+  /// position of the light source, to build the ray from the dot to the light.
+  ///
+  /// The sun has no position, so the far position opposite to the sun direction is used.
+  /// It is different for each dot, so the rays to the sun are parallel
+  pub fn position_for(&self, dot: &Dot) -> Dot {
+    match self.kind {
+      LightKind::Sun { direction } => dot.offset(&Spear::from_array(direction).back(), SUN_DISTANCE),
+      LightKind::Lamp { position, .. } => Dot::from_array(position),
+    }
+  }
+
+  /// check the dot is inside the power distance. The sun has no power distance limit
+  pub fn is_in_power(&self, dot: &Dot) -> bool {
+    match self.kind {
+      LightKind::Sun { .. } => true,
+      LightKind::Lamp { power, position, .. } => dot.d_dot(&Dot::from_array(position)) < power,
+    }
+  }
+
+  /// check the ball is farther than the power distance. The sun is never too far
+  pub fn is_too_far(&self, center: &Dot, radius: f64) -> bool {
+    match self.kind {
+      LightKind::Sun { .. } => false,
+      LightKind::Lamp { power, position, .. } => {
+        let lamp = Dot::from_array(position);
+        let v_light = Spear::pp(&[*center, lamp]);
+        let nearest_dot = center.offset(&v_light, radius);
+        lamp.d_dot(&nearest_dot) > power
+      }
+    }
+  }
+
+  /// check the plane is farther than the power distance. The sun is never too far
+  pub fn is_mat_too_far(&self, mat: &Mat) -> bool {
+    match self.kind {
+      LightKind::Sun { .. } => false,
+      LightKind::Lamp { power, position, .. } => Dot::from_array(position).d_mat(mat) > power,
+    }
+  }
+
+  /// the light brightness in the dot, from 0(no light) to 1(full light).
+  ///
+  /// Decreased by the distance to the power distance, and by the spot edge
+  pub fn power_coef(&self, dot: &Dot) -> f64 {
+    let LightKind::Lamp { power, position, shape } = self.kind else { return 1.0 };
+    let position = Dot::from_array(position);
+    let distance_coef = (power - dot.d_dot(&position)).max(0.0) / power;
+    match shape {
+      LampShape::Point | LampShape::Rect { .. } | LampShape::Sphere { .. } => distance_coef,
+      LampShape::Spot { direction, angle, edge } => {
+        let off_axis = Spear::from_array(direction).ang_deg(&Spear::pp(&[position, *dot]));
+        // smooth fade out through the edge
+        let spot_coef = if off_axis <= angle {
+          1.0
+        } else if off_axis >= angle + edge {
+          0.0
+        } else {
+          let x = (angle + edge - off_axis) / edge;
+          x * x * (3.0 - 2.0 * x)
+        };
+        distance_coef * spot_coef
+      }
+    }
+  }
+
+  /// points of the light source, to cast the shadow rays from the dot to them.
+  ///
+  /// The area light surface is covered by the samples points evenly, the points are the same for each dot
+  /// (golden ratio sequence), so there is no noise. Other kinds of the light are one point
+  pub fn sample_positions(&self, dot: &Dot) -> Vec<Dot> {
+    let LightKind::Lamp { position, shape, .. } = self.kind else { return vec![self.position_for(dot)] };
+    let center = Dot::from_array(position);
+    // pairs of the coordinates from 0 to 1, spread evenly over the square
+    let samples = self.samples;
+    let pair = |i: usize| ((i as f64 + 0.5) / samples as f64, (i as f64 * GOLDEN_RATIO).fract());
+
+    match shape {
+      LampShape::Rect { edge_u, edge_v } => (0..samples)
+        .map(|i| {
+          let (u, v) = pair(i);
+          let (u, v) = (u - 0.5, v - 0.5);
+          Dot::new(
+            center.x + edge_u[0] * u + edge_v[0] * v,
+            center.y + edge_u[1] * u + edge_v[1] * v,
+            center.z + edge_u[2] * u + edge_v[2] * v,
+          )
+        })
+        .collect(),
+      LampShape::Sphere { radius } => {
+        // the sphere looks like the disk from the dot, so the disk across the direction to the dot is covered
+        let to_dot = Spear::pp(&[center, *dot]);
+        let helper = if to_dot.is_ll(&Spear::oy()) { Spear::ox() } else { Spear::oy() };
+        let a = to_dot.normal(&helper);
+        let b = to_dot.normal(&a);
+        (0..samples)
+          .map(|i| {
+            let (u, v) = pair(i);
+            let r = radius * u.sqrt();
+            let angle = 2.0 * std::f64::consts::PI * v;
+            center.offset(&a, r * angle.cos()).offset(&b, r * angle.sin())
+          })
+          .collect()
+      }
+      _ => vec![center],
+    }
+  }
+
+}
@@ -0,0 +1,76 @@
+use crate::{parser::camera_file::Pass, tracer::pixel_::PassHit};
+
+/// the rendered pictures, three components of each pixel, row by row.
+///
+/// The radiance before the tone mapping, and the values of the camera passes, in the same order as the camera passes
+pub struct Frame {
+  pub radiance: Vec<f64>,
+  pub passes: Vec<Vec<f64>>,
+}
+
+impl Pass {
+  /// the end of the pass picture file name, before the extension
+  pub fn suffix(&self) -> &'static str {
+    match self {
+      Pass::Depth => "_depth",
+      Pass::Normal => "_normal",
+      Pass::Id => "_id",
+      Pass::Shadow => "_shadow",
+    }
+  }
+
+  /// the output file name with the suffix, f.e. "x_depth.png" for "x.png", the picture kind is the same
+  pub fn file_name(&self, output_file_name: &str) -> String {
+    match output_file_name.rfind('.') {
+      Some(dot) => format!("{}{}{}", &output_file_name[..dot], self.suffix(), &output_file_name[dot..]),
+      None => format!("{}{}", output_file_name, self.suffix()),
+    }
+  }
+
+  /// the pass values of the pixel, from the nearest hit of the ray through it, None if nothing is hit.
+  ///
+  /// The depth, id and shadow are the same in all three components, the normal is x y z.
+  /// Without the hit, the depth and the normal are 0, the id is -1, and the shadow is 0.
+  /// The shadow is found by the shading, together with the color, so no rays are traced here
+  pub fn values(&self, pass_hit: Option<&PassHit>) -> [f64; 3] {
+    let Some(PassHit { hit, shadow }) = pass_hit else {
+      return if *self == Pass::Id { [-1.0; 3] } else { [0.0; 3] }
+    };
+    match self {
+      Pass::Depth => [hit.t; 3],
+      Pass::Normal => hit.normal.to_array(),
+      Pass::Id => [hit.index as f64; 3],
+      Pass::Shadow => [*shadow; 3],
+    }
+  }
+
+  /// the pass values as the picture brightness(0 to 1), used instead of the tone mapping.
+  ///
+  /// The nearest hit is white and the farther ones are darker, the normal -1..1 is 0..1,
+  /// each id has own color, the shadow is as is. Nothing hit is black(the normal is gray)
+  pub fn display(&self, values: &[f64]) -> Vec<f64> {
+    match self {
+      Pass::Depth => {
+        let nearest = values.iter().copied().filter(|&t| t > 0.0).fold(f64::INFINITY, f64::min);
+        values.iter().map(|&t| if t > 0.0 { nearest / t } else { 0.0 }).collect()
+      }
+      Pass::Normal => values.iter().map(|&n| n * 0.5 + 0.5).collect(),
+      Pass::Id => values
+        .chunks_exact(3)
+        .flat_map(|pixel| id_color(pixel[0]))
+        .collect(),
+      Pass::Shadow => values.iter().map(|&s| s.clamp(0.0, 1.0)).collect(),
+    }
+  }
+}
+
+/// the bright color of the object index, the neighbour indexes have very different hues.
+/// Black if nothing is hit
+fn id_color(id: f64) -> [f64; 3] {
+  if id < 0.0 {
+    return [0.0; 3];
+  }
+  // the golden ratio steps spread the hues evenly for any number of the objects
+  let hue = (id * 0.618_033_988_75).fract();
+  [0.0, 1.0, 2.0].map(|k| 0.5 + 0.5 * (2.0 * std::f64::consts::PI * (hue + k / 3.0)).cos())
+}
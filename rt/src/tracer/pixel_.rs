@@ -1,21 +1,34 @@
 use crate::{
-    gem::{dot::Dot, gem::Gem, mat::Mat, spear::Spear, utils::F64xyz},
-    parser::objects_file::Objects,
+    gem::{bvh::Bvh, dot::Dot, hit::Hit, gem::Gem, mat::Mat, spear::Spear, utils::F64xyz},
+    parser::{light_file::Light, objects_file::{Material, Objects}},
 };
 
-use std::sync::Arc;
+use super::{sample::Rng, scene::Scene};
+use crate::printer::tone::srgb_to_linear;
 
-use super::scene::Scene;
+/// the color brightness division factor, for the back side of the object, or the shadow
+const DARK: f64 = 2.0;
 
+/// the nearest hit of the ray, and the part(0 to 1) of the light not reaching it, for the camera passes
+#[derive(Debug, Clone, Copy)]
+pub struct PassHit {
+    pub hit: Hit,
+    /// hidden by the objects between, or by the dark side of the hit object, averaged by the lights power
+    pub shadow: f64,
+}
+
+/// the color as the light radiance(linear), 1 is the 255 color component, before the tone mapping.
+///
+/// The colors are not limited, so the light from several sources, or the reflections, add up properly
 pub struct RGB {
-    pub r: u8,
-    pub g: u8,
-    pub b: u8,
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
     pub fresh: bool,
 }
 
 impl RGB {
-    pub fn new(r: u8, g: u8, b: u8) -> RGB {
+    pub fn new(r: f64, g: f64, b: f64) -> RGB {
         RGB {
             r,
             g,
@@ -24,8 +37,14 @@ impl RGB {
         }
     }
 
+    /// the color from the 0-255 components(sRGB) of the scene files, decoded to the linear radiance
     pub fn from_array(array: &[u8; 3]) -> RGB {
-        RGB::new(array[0], array[1], array[2])
+        let [r, g, b] = array.map(|c| srgb_to_linear(c as f64 / 255.0));
+        RGB::new(r, g, b)
+    }
+
+    pub fn to_array(&self) -> [f64; 3] {
+        [self.r, self.g, self.b]
     }
 
     /// the same color
@@ -33,9 +52,19 @@ impl RGB {
         RGB::new(self.r, self.g, self.b)
     }
 
+    /// the start color to add the colors from the light sources
+    pub fn black() -> RGB {
+        RGB::new(0.0, 0.0, 0.0)
+    }
+
+    /// sum of the colors
+    pub fn plus(&self, other: &RGB) -> RGB {
+        RGB::new(self.r + other.r, self.g + other.g, self.b + other.b)
+    }
+
     /// cyan color was choosen as the background color, if the ray does not hit any object properly (f.e.: hit the plane outside the light power distance, or just miss any object)
     pub fn background() -> RGB {
-        RGB::new(0, 255, 255)
+        RGB::from_array(&[0, 255, 255])
     }
 
     /// decrease the color brightness to represent the back side of the object
@@ -47,8 +76,8 @@ impl RGB {
     /// after that, the same color is returned
     pub fn dark_side(&self) -> RGB {
         if self.fresh {
-            let dark = 2;
-            let mut rgb = RGB::new(self.r / dark, self.g / dark, self.b / dark);
+            let dark = 1.0 / DARK;
+            let mut rgb = RGB::new(self.r * dark, self.g * dark, self.b * dark);
             rgb.fresh = false;
             rgb
         } else {
@@ -56,92 +85,439 @@ impl RGB {
         }
     }
 
-    /// crete color affected by the light power (simple simulation, not a proper one)
-    pub fn power_affected(
-        rgb: [u8; 3],
+    /// the color filtered by the other one, each component is multiplied by the other one
+    pub fn filtered(&self, other: &RGB) -> RGB {
+        RGB::new(self.r * other.r, self.g * other.g, self.b * other.b)
+    }
+
+    /// the color filtered by the tint, each component is multiplied by the linear tint part of 255
+    pub fn tinted(&self, t: [u8; 3]) -> RGB {
+        self.filtered(&RGB::from_array(&t))
+    }
+
+    /// mix of the colors, part is the share of the other color, from 0 to 1
+    pub fn mix(&self, other: &RGB, part: f64) -> RGB {
+        self.mix_parts(other, [part; 3])
+    }
+
+    /// mix of the colors, with the own part of the other color for each component
+    pub fn mix_parts(&self, other: &RGB, parts: [f64; 3]) -> RGB {
+        let mix = |a: f64, b: f64, part: f64| a * (1.0 - part) + b * part;
+        RGB::new(
+            mix(self.r, other.r, parts[0]),
+            mix(self.g, other.g, parts[1]),
+            mix(self.b, other.b, parts[2]),
+        )
+    }
+
+    /// the color brightness added by the light, by the angle between the surface and the light (Lambert)
+    ///
+    /// The surface faced to the light gets the color over the dark side, the surface along the light ray
+    /// gets nothing, so with the dark side added once for the hit, there is no step between them.
+    /// Use it on the fresh color only, the dark side already has the lowest brightness
+    pub fn diffuse(&self, normal: &Spear, color_position: Dot, light_source_position: Dot) -> RGB {
+        let to_light = Spear::pp(&[color_position, light_source_position]);
+        let cos = normal.scalar(&to_light).max(0.0);
+        let coef = (1.0 - 1.0 / DARK) * cos;
+        RGB::new(self.r * coef, self.g * coef, self.b * coef)
+    }
+
+    /// add the highlight of the light source, reflected to the viewer (Blinn-Phong)
+    ///
+    /// The highlight is the light color, decreased by the light brightness(power_coef),
+    /// the same way as the object color in power_affected.
+    /// Use it on the fresh color only, there is no highlight in the shadow
+    #[allow(clippy::too_many_arguments)]
+    pub fn specular(
+        &self,
+        material: &Material,
+        normal: &Spear,
+        view: &Spear,
         color_position: Dot,
         light_source_position: Dot,
         light_color: RGB,
-        light_power_distance: f64,
+        power_coef: f64,
     ) -> RGB {
-        let mut rgb = RGB::from_array(&rgb);
-        let distance = color_position.d_dot(&light_source_position);
-        if distance < light_power_distance {
-            let power_coef = (light_power_distance - distance) / light_power_distance;
+        if material.specular <= 0.0 || power_coef <= 0.0 {
+            return self.same();
+        }
 
-            let shade_r = light_color.r as f64 / 255_f64;
-            let shade_g = light_color.g as f64 / 255_f64;
-            let shade_b = light_color.b as f64 / 255_f64;
+        // the halfway vector between the directions to the light and to the viewer
+        let to_light = Spear::pp(&[color_position, light_source_position]);
+        let halfway = to_light.add(view);
+        if halfway.is_zero() || normal.scalar(&to_light) <= 0.0 {
+            return self.same();
+        }
+        let highlight = material.specular * power_coef * normal.scalar(&halfway).max(0.0).powf(material.shininess);
 
-            rgb.r = (rgb.r as f64 * power_coef * shade_r) as u8;
-            rgb.g = (rgb.g as f64 * power_coef * shade_g) as u8;
-            rgb.b = (rgb.b as f64 * power_coef * shade_b) as u8;
+        RGB::new(
+            self.r + light_color.r * highlight,
+            self.g + light_color.g * highlight,
+            self.b + light_color.b * highlight,
+        )
+    }
 
-            rgb
-        } else {
-            RGB::background()
+    /// the light angle and the highlight of the light source, for the color which is not on the dark side.
+    ///
+    /// It is the light added over the dark side, the dark side itself is not included.
+    /// The viewer is the ray origin, so the view direction is back along the ray
+    pub fn shaded(&self, material: &Material, hit: &Hit, view: &Spear, light: &Light) -> RGB {
+        if !self.fresh {
+            return self.same();
         }
+        let light_position = light.position_for(&hit.dot);
+        self.diffuse(&hit.normal, hit.dot, light_position).specular(
+            material,
+            &hit.normal,
+            view,
+            hit.dot,
+            light_position,
+            RGB::from_array(&light.color),
+            light.power_coef(&hit.dot),
+        )
+    }
+
+    /// crete color affected by the light power (simple simulation, not a proper one).
+    ///
+    /// The power_coef is the light brightness in the color position, from 0 to 1
+    pub fn power_affected(rgb: [u8; 3], power_coef: f64, light_color: RGB) -> RGB {
+        let rgb = RGB::from_array(&rgb);
+        RGB::new(
+            rgb.r * power_coef * light_color.r,
+            rgb.g * power_coef * light_color.g,
+            rgb.b * power_coef * light_color.b,
+        )
     }
 }
 
 impl Scene {
-    pub fn pixel_color(&self, row: usize, col: usize, good_to_trace: &[Arc<Objects>]) -> RGB {
-        let ray = self.camera_ray_to_pixel(row, col);
-
-        let mut rgb = RGB::background();
-        let mut nearest_position = Dot::maximum();
-
-        // Create `temp_good_to_trace` outside of the loop
-        let temp_good_to_trace: Vec<Objects> =
-            good_to_trace.iter().map(|arc| (**arc).clone()).collect();
-
-        // iterate through the objects to find the nearest intersection with the ray
-        for (index, arc_object) in good_to_trace.iter().enumerate() {
-            let object = &**arc_object;
-            match object {
-                Objects::Mat { .. } => {
-                    (rgb, nearest_position) = self.check_mat(
-                        rgb,
-                        nearest_position,
-                        ray,
-                        object,
-                        index,
-                        &temp_good_to_trace,
-                    );
-                }
-                Objects::Ball { .. } => {
-                    (rgb, nearest_position) = self.check_ball(
-                        rgb,
-                        nearest_position,
-                        ray,
-                        &object,
-                        index,
-                        &temp_good_to_trace,
-                    );
-                }
-                Objects::Box { .. } => {
-                    (rgb, nearest_position) = self.check_box(
-                        rgb,
-                        nearest_position,
-                        ray,
-                        &object,
-                        index,
-                        &temp_good_to_trace,
-                    );
+    /// check the dot is inside the power distance of any light source
+    pub fn is_lit(&self, dot: &Dot) -> bool {
+        self.lights.iter().any(|light| light.is_in_power(dot))
+    }
+
+    /// intersection of the ray and any object, the first one along the ray.
+    ///
+    /// If there is no intersection than return None
+    pub fn ray_x_object(ray: &Mat, object: &Objects) -> Option<Hit> {
+        match *object {
+            Objects::Mat {
+                position, normal, ..
+            } => Gem::ray_x_mat(
+                ray,
+                &Mat::new(Dot::from_array(position), Spear::from_array(normal)),
+            ),
+            Objects::Ball {
+                position, radius, ..
+            } => Gem::ray_x_ball(ray, &Dot::from_array(position), radius),
+            Objects::Box {
+                position,
+                size,
+                axes,
+                ..
+            } => Gem::ray_x_box(ray, &Dot::from_array(position), size, axes),
+            Objects::Roll {
+                position,
+                radius,
+                height,
+                axis,
+                ..
+            } => Gem::ray_x_roll(ray, &Dot::from_array(position), radius, height, axis),
+            Objects::Cone {
+                position,
+                radius,
+                height,
+                axis,
+                ..
+            } => Gem::ray_x_cone(ray, &Dot::from_array(position), radius, height, axis),
+            Objects::Disk {
+                position,
+                radius,
+                normal,
+                ..
+            } => Gem::ray_x_disk(ray, &Dot::from_array(position), radius, normal),
+            Objects::Torus {
+                position,
+                major_radius,
+                minor_radius,
+                axis,
+                ..
+            } => Gem::ray_x_torus(
+                ray,
+                &Dot::from_array(position),
+                major_radius,
+                minor_radius,
+                axis,
+            ),
+            Objects::Mesh { ref mesh, .. } => {
+                Gem::ray_x_mesh(ray, &mesh.triangles, &mesh.bvh)
+            }
+        }
+    }
+
+    /// both intersections of the ray line and the convex object(ball, box, roll), the near and the far ones.
+    ///
+    /// None for other objects, or if there is no intersection
+    pub fn ray_x_object_both(ray: &Mat, object: &Objects) -> Option<[Hit; 2]> {
+        match *object {
+            Objects::Ball {
+                position, radius, ..
+            } => Gem::ray_x_ball_both(ray, &Dot::from_array(position), radius),
+            Objects::Box {
+                position,
+                size,
+                axes,
+                ..
+            } => Gem::ray_x_box_both(ray, &Dot::from_array(position), size, axes),
+            Objects::Roll {
+                position,
+                radius,
+                height,
+                axis,
+                ..
+            } => Gem::ray_x_roll_both(ray, &Dot::from_array(position), radius, height, axis),
+            _ => None,
+        }
+    }
+
+    /// the box(oriented along axes) around the object, as \[min corner, max corner\].
+    ///
+    /// None for the infinite plane(mat)
+    pub fn object_bounds(object: &Objects) -> Option<[[f64; 3]; 2]> {
+        // half size of the round gap(circle) around the axis, projected to each global axis
+        let circle = |axis: [f64; 3], radius: f64| axis.map(|a| radius * (1.0 - a * a).max(0.0).sqrt());
+        let around = |center: [f64; 3], half: [f64; 3]| {
+            [
+                [center[0] - half[0], center[1] - half[1], center[2] - half[2]],
+                [center[0] + half[0], center[1] + half[1], center[2] + half[2]],
+            ]
+        };
+
+        match object {
+            Objects::Mat { .. } => None,
+            Objects::Ball {
+                position, radius, ..
+            } => Some(around(*position, [radius.abs(); 3])),
+            Objects::Box {
+                position,
+                size,
+                axes,
+                ..
+            } => {
+                // each local axis edge projected to the global axes
+                let mut half = [0.0; 3];
+                for (axis, size) in axes.iter().zip(size.iter()) {
+                    for (h, a) in half.iter_mut().zip(axis.iter()) {
+                        *h += (a * size / 2.0).abs();
+                    }
                 }
-                Objects::Roll { .. } => {
-                    (rgb, nearest_position) = self.check_roll(
-                        rgb,
-                        nearest_position,
-                        ray,
-                        &object,
-                        index,
-                        &temp_good_to_trace,
-                    );
+                Some(around(*position, half))
+            }
+            Objects::Roll {
+                position,
+                radius,
+                height,
+                axis,
+                ..
+            } => {
+                let gap = circle(*axis, radius.abs());
+                Some(around(
+                    *position,
+                    [0, 1, 2].map(|i| gap[i] + (axis[i] * height / 2.0).abs()),
+                ))
+            }
+            Objects::Cone {
+                position,
+                radius,
+                height,
+                axis,
+                ..
+            } => {
+                // the box around the base, extended to the apex
+                let [mut min, mut max] = around(*position, circle(*axis, radius.abs()));
+                for i in 0..3 {
+                    let apex = position[i] + axis[i] * height;
+                    min[i] = min[i].min(apex);
+                    max[i] = max[i].max(apex);
                 }
+                Some([min, max])
             }
+            Objects::Disk {
+                position,
+                radius,
+                normal,
+                ..
+            } => Some(around(
+                *position,
+                circle(Spear::from_array(*normal).to_array(), radius.abs()),
+            )),
+            Objects::Torus {
+                position,
+                major_radius,
+                minor_radius,
+                axis,
+                ..
+            } => Some(around(
+                *position,
+                circle(*axis, major_radius.abs()).map(|h| h + minor_radius.abs()),
+            )),
+            Objects::Mesh { mesh, .. } => Some([mesh.min, mesh.max]),
         }
+    }
 
-        rgb
+    /// the color of the pixel, and the nearest hit of the ray through the pixel center, None if nothing is hit.
+    ///
+    /// With several rays through the pixel, the hit is the one of the ray nearest to the pixel center,
+    /// with the light hidden from it found by the same shading, which colors the pixel
+    pub fn pixel_color(&self, row: usize, col: usize, good_to_trace: &[Objects], bvh: &Bvh) -> (RGB, Option<PassHit>) {
+        if self.camera.samples == 1 && self.camera.aperture <= 0.0 {
+            let ray = self.camera_ray_to_pixel(row, col);
+            return self.ray_hit_color(ray, self.camera.depth, good_to_trace, bvh);
+        }
+
+        // several rays through the pixel, spread by the pattern, smooth the edges of the objects,
+        // and start from the different points of the lens, blur the objects out of the focus
+        let mut rng = Rng::for_pixel(row, col, self.camera.width);
+        let offsets = self.camera.pattern.offsets(self.camera.samples, &mut rng);
+        let mut sum = RGB::black();
+        // the squared distance of the ray from the pixel center, and its hit
+        let mut center: Option<(f64, Option<PassHit>)> = None;
+        for (dr, dc) in &offsets {
+            let ray = self.camera_lens_ray(row as f64 + dr, col as f64 + dc, &mut rng);
+            let (color, hit) = self.ray_hit_color(ray, self.camera.depth, good_to_trace, bvh);
+            sum = sum.plus(&color);
+            let distance = dr * dr + dc * dc;
+            if center.as_ref().is_none_or(|(nearest, _)| distance < *nearest) {
+                center = Some((distance, hit));
+            }
+        }
+        let n = offsets.len() as f64;
+        (RGB::new(sum.r / n, sum.g / n, sum.b / n), center.and_then(|(_, hit)| hit))
+    }
+
+    /// color of the nearest object hit by the ray, or the background color.
+    ///
+    /// The reflective object mixes its color with the color of the reflected ray,
+    /// and the transparent object with the color of the light come through it.
+    /// The secondary rays are traced the same way, while the depth(number of the reflections left) is not over
+    pub fn ray_color(&self, ray: Mat, depth: usize, good_to_trace: &[Objects], bvh: &Bvh) -> RGB {
+        self.ray_hit_color(ray, depth, good_to_trace, bvh).0
+    }
+
+    /// the same as ray_color, plus the nearest hit of the ray with the light hidden from it, None if nothing is hit
+    pub fn ray_hit_color(&self, ray: Mat, depth: usize, good_to_trace: &[Objects], bvh: &Bvh) -> (RGB, Option<PassHit>) {
+        match self.nearest_hit(&ray, good_to_trace, bvh) {
+            Some(hit) => {
+                let (color, shadow) = self.hit_color(&ray, &hit, depth, good_to_trace, bvh);
+                (color, Some(PassHit { hit, shadow }))
+            }
+            None => (RGB::background(), None),
+        }
+    }
+
+    /// the nearest hit of the ray, None if nothing is hit. Only the intersections, without the colors.
+    ///
+    /// Iterate through the objects, which boxes are hit by the ray, the lit ones only
+    pub fn nearest_hit(&self, ray: &Mat, good_to_trace: &[Objects], bvh: &Bvh) -> Option<Hit> {
+        bvh.candidates(ray)
+            .into_iter()
+            .fold(None, |nearest, index| self.check_object(nearest, ray, index, good_to_trace))
+    }
+
+    /// the hit of the ray and the object, if it is the new nearest one, otherwise the old nearest hit.
+    ///
+    /// The hit outside the power distance of all the light sources is ignored, like there is no object
+    fn check_object(&self, nearest: Option<Hit>, ray: &Mat, index: usize, good_to_trace: &[Objects]) -> Option<Hit> {
+        match Scene::ray_x_object(ray, &good_to_trace[index]) {
+            Some(hit) if hit.is_closer(&nearest) && self.is_lit(&hit.dot) => Some(hit.with_index(index)),
+            _ => nearest,
+        }
+    }
+
+    /// check the light source position is on the dark side of the hit object.
+    ///
+    /// The flat mat has no dark side, the flat disk has the side of its plane, opposite to the light.
+    /// For the other objects the light ray hits the object itself before the hit point
+    pub fn dark_side_check<'a>(ray: &Mat, hit: &Hit, object: &'a Objects) -> Box<dyn Fn(Dot) -> bool + 'a> {
+        match *object {
+            Objects::Mat { .. } => Box::new(|_| false),
+            Objects::Disk { position, normal, .. } => {
+                let disk_mat = Mat::new(Dot::from_array(position), Spear::from_array(normal));
+                let origin = ray.origin;
+                Box::new(move |light_position: Dot| origin.is_above(&disk_mat) != light_position.is_above(&disk_mat))
+            }
+            _ => {
+                let hit_dot = hit.dot;
+                Box::new(move |light_position: Dot| {
+                    let light_ray = Mat::new(light_position, Spear::pp(&[light_position, hit_dot]));
+                    Scene::ray_x_object(&light_ray, object)
+                        .is_some_and(|light_hit| light_hit.t < f64::Z9X9 * hit_dot.d_dot(&light_position))
+                })
+            }
+        }
+    }
+
+    /// color of the hit object, lit by all the light sources, and the part of the light hidden from the hit.
+    ///
+    /// The reflective object mixes its color with the color of the reflected ray,
+    /// and the transparent object with the color of the light come through it
+    fn hit_color(&self, ray: &Mat, hit: &Hit, depth: usize, good_to_trace: &[Objects], bvh: &Bvh) -> (RGB, f64) {
+        let object = &good_to_trace[hit.index];
+        let material = object.material();
+        let is_dark_side = Scene::dark_side_check(ray, hit, object);
+        let (mut rgb, shadow) = self.lit_color(ray, hit, object.color(), &material, &*is_dark_side, good_to_trace, bvh);
+
+        if material.reflect > 0.0 && depth > 0 {
+            let reflected = self.ray_color(hit.reflected(ray), depth - 1, good_to_trace, bvh);
+            rgb = rgb.mix(&reflected, material.reflect);
+        }
+        if material.transparency > 0.0 && depth > 0 {
+            let through = self.glass_color(ray, hit, object, depth, good_to_trace, bvh);
+            rgb = rgb.mix(&through, material.transparency);
+        }
+        (rgb, shadow)
+    }
+
+    /// color of the hit point with the color and material, the colors from all the light sources are added.
+    ///
+    /// The dark side color is added once, the brightest of the dark sides of all the lights(by each component),
+    /// so the shadows do not get brighter with more lights. For each point of each light source, find the light
+    /// come through the other objects between the hit point and it. The opaque objects hide the point,
+    /// the transparent ones tint the light. Each light adds its shaded color over the dark side, by the part come through.
+    /// The part of the light hidden from the hit is averaged by the lights power, the ones too far away are skipped
+    #[allow(clippy::too_many_arguments)]
+    fn lit_color(
+        &self,
+        ray: &Mat,
+        hit: &Hit,
+        color: [u8; 3],
+        material: &Material,
+        is_dark_side: &dyn Fn(Dot) -> bool,
+        good_to_trace: &[Objects],
+        bvh: &Bvh,
+    ) -> (RGB, f64) {
+        let mut pixel_color = RGB::black();
+        let mut dark_side = RGB::black();
+        let mut hidden = 0.0;
+        let mut total = 0.0;
+        for light in &self.lights {
+            let power_coef = light.power_coef(&hit.dot);
+            if power_coef <= 0.0 { continue }
+            let rgb = RGB::power_affected(
+                color,
+                power_coef,
+                RGB::from_array(&light.color),
+            );
+            let dark = rgb.dark_side();
+            dark_side = RGB::new(dark_side.r.max(dark.r), dark_side.g.max(dark.g), dark_side.b.max(dark.b));
+
+            // the part of the light come through the objects between, of the lit color over the dark side
+            let through = Scene::light_through(hit, light, is_dark_side, good_to_trace, bvh);
+            let [r, g, b] = through;
+            pixel_color = pixel_color.plus(&rgb.shaded(material, hit, &ray.normal.back(), light).filtered(&RGB::new(r, g, b)));
+            hidden += power_coef * Scene::hidden_part(through);
+            total += power_coef;
+        }
+        (dark_side.plus(&pixel_color), if total > 0.0 { hidden / total } else { 0.0 })
     }
 }
@@ -0,0 +1,62 @@
+use crate::parser::camera_file::SamplePattern;
+
+/// the start value of the random numbers, the same one for each render,
+/// so the same scene gives the same picture
+const SEED: u64 = 2023;
+
+/// pseudo random numbers generator (SplitMix64), small and fast enough for the sampling
+pub struct Rng {
+  state: u64,
+}
+
+impl Rng {
+  /// the generator for the pixel, the pixels are rendered in parallel,
+  /// so each one has own generator, which does not depend on the render order
+  pub fn for_pixel(row: usize, col: usize, width: usize) -> Rng {
+    Rng { state: SEED ^ (row * width + col) as u64 }
+  }
+
+  /// the next random number, from 0 inclusive to 1 exclusive
+  pub fn next_f64(&mut self) -> f64 {
+    self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = self.state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+  }
+
+  /// the random point inside the circle of radius 1, spread evenly over the area
+  pub fn next_in_disk(&mut self) -> (f64, f64) {
+    let r = self.next_f64().sqrt();
+    let angle = 2.0 * std::f64::consts::PI * self.next_f64();
+    (r * angle.cos(), r * angle.sin())
+  }
+}
+
+impl SamplePattern {
+  /// the displacements of the rays from the pixel center, down and right, from -0.5 to 0.5 of the pixel
+  pub fn offsets(&self, samples: usize, rng: &mut Rng) -> Vec<(f64, f64)> {
+    match self {
+      SamplePattern::Jittered => (0..samples)
+        .map(|_| (rng.next_f64() - 0.5, rng.next_f64() - 0.5))
+        .collect(),
+      SamplePattern::Grid | SamplePattern::Stratified => {
+        // the number of the samples is square, checked by the camera file parser
+        let side = (samples as f64).sqrt().round() as usize;
+        let cell = 1.0 / side as f64;
+        let mut offsets = Vec::with_capacity(samples);
+        for row in 0..side {
+          for col in 0..side {
+            let (dr, dc) = match self {
+              SamplePattern::Stratified => (rng.next_f64(), rng.next_f64()),
+              _ => (0.5, 0.5),
+            };
+            offsets.push(((row as f64 + dr) * cell - 0.5, (col as f64 + dc) * cell - 0.5));
+          }
+        }
+        offsets
+      }
+    }
+  }
+}
@@ -5,15 +5,15 @@ use crate::{
 
 pub struct Scene {
   pub camera: Camera,
-  pub light: Light,
+  pub lights: Vec<Light>,
   pub objects: Vec<Objects>,
 }
 
 impl Scene {
-  pub fn new(camera: Camera, light: Light, objects: Vec<Objects>) -> Scene {
+  pub fn new(camera: Camera, lights: Vec<Light>, objects: Vec<Objects>) -> Scene {
     Scene {
       camera,
-      light,
+      lights,
       objects,
     }
   }
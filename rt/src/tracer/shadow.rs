@@ -0,0 +1,78 @@
+use crate::{
+  gem::{bvh::Bvh, hit::Hit, dot::Dot, spear::Spear, mat::Mat},
+  parser::{light_file::Light, objects_file::Objects},
+  tracer::{scene::Scene, pixel_::RGB}
+};
+
+impl Scene {
+
+  /// the light come from the light position to the dot, through the objects between them.
+  ///
+  /// Each component is the part(0 to 1) let through. White if nothing is between,
+  /// black if some opaque object is between.
+  /// The transparent object lets through its transparency part, filtered by its color and tint.
+  /// The object with index(the one the dot is on) is skipped, its own shadow is the dark side
+  pub fn transmittance(
+    dot: &Dot,
+    light_position: &Dot,
+    index: usize,
+    good_to_trace: &[Objects],
+    bvh: &Bvh,
+  ) -> RGB {
+    let distance_to_light = dot.d_dot(light_position);
+    let ray_to_light = Mat::new(
+      *dot,
+      Spear::pp(
+        &[
+          *dot,
+          *light_position,
+        ]
+      )
+    );
+
+    let mut through = RGB::new(1.0, 1.0, 1.0);
+    for other in bvh.candidates(&ray_to_light) {
+      if other == index { continue }
+      let object = &good_to_trace[other];
+      let other_hit = Scene::ray_x_object(&ray_to_light, object);
+      if !other_hit.is_some_and(|other_hit| other_hit.t < distance_to_light) { continue }
+
+      let material = object.material();
+      // no need to check other objects, the light is blocked
+      if material.transparency <= 0.0 { return RGB::black() }
+      let filter = RGB::black().mix(&RGB::from_array(&object.color()).tinted(material.tint), material.transparency);
+      through = through.filtered(&filter);
+    }
+    through
+  }
+
+  /// the light come from all the points of the light source to the hit point,
+  /// as the parts(0 to 1) of the red, green and blue components.
+  ///
+  /// The transmittance of each point is averaged, the points on the dark side
+  /// of the object with the hit are hidden
+  pub fn light_through(
+    hit: &Hit,
+    light: &Light,
+    is_dark_side: &dyn Fn(Dot) -> bool,
+    good_to_trace: &[Objects],
+    bvh: &Bvh,
+  ) -> [f64; 3] {
+    let light_positions = light.sample_positions(&hit.dot);
+    let mut parts = [0.0; 3];
+    for light_position in &light_positions {
+      if is_dark_side(*light_position) { continue }
+      let through = Scene::transmittance(&hit.dot, light_position, hit.index, good_to_trace, bvh);
+      for (part, c) in parts.iter_mut().zip(through.to_array()) {
+        *part += c;
+      }
+    }
+    parts.map(|part| part / light_positions.len() as f64)
+  }
+
+  /// the part(0 to 1) of the light not reaching the hit, from the parts of the red, green and blue come through
+  pub fn hidden_part(through: [f64; 3]) -> f64 {
+    1.0 - through.iter().sum::<f64>() / 3.0
+  }
+
+}
@@ -1,29 +1,57 @@
-use super::scene::Scene;
+use super::{passes::Frame, scene::Scene};
+
+use crate::{gem::bvh::Bvh, parser::camera_file::Projection};
 
 use rayon::prelude::*;
-use std::sync::Arc;
 
 impl Scene {
-    pub fn trace(&self) -> Vec<u8> {
+    /// the radiance of the pixels, three components of each one, row by row, before the tone mapping,
+    /// and the camera passes, from the same hits
+    pub fn trace(&self) -> Frame {
         let camera = &self.camera;
         let width = camera.width;
         let height = camera.height;
 
-        // Pre-calculate values for good_to_trace and avoid cloning inside the loop
-        let good_to_trace = Arc::new(self.good_to_trace(&self.objects));
-        let good_to_trace_ref = Arc::as_ref(&good_to_trace);
+        // Pre-calculate values for good_to_trace, and the tree of the boxes around them,
+        // to check only the objects which can be hit by the ray.
+        // The wrong objects and the ones too far from the lights are dropped always.
+        // The reflected and refracted rays can hit the objects out of the camera view,
+        // the rays from the lens edge can see a bit out of the camera view too,
+        // and the camera view planes are built for the perspective projection only,
+        // so the objects out of the view are dropped without them only
+        let reflections = camera.depth > 0
+            && self.objects.iter().any(|o| {
+                let material = o.material();
+                material.reflect > 0.0 || material.transparency > 0.0
+            });
+        let in_view_only = !reflections && camera.aperture <= 0.0 && camera.projection == Projection::Perspective;
+        let good_to_trace = self.good_to_trace(&self.objects, in_view_only);
+        let bounds: Vec<_> = good_to_trace.iter().map(Scene::object_bounds).collect();
+        let bvh = Bvh::new(&bounds);
+
+        // one ray through each pixel, and more rays through the edge pixels only
+        let pixels = if camera.adaptive > 0 {
+            self.trace_adaptive(&good_to_trace, &bvh)
+        } else {
+            (0..(width * height))
+                .into_par_iter()
+                .map(|i| self.pixel_color(i / width, i % width, &good_to_trace, &bvh))
+                .collect()
+        };
 
-        // Flatten the iteration over pixels
-        (0..(width * height))
-            .into_par_iter()
-            .map(|i| {
-                let row = i / width;
-                let col = i % width;
-                // Use the reference directly without cloning
-                let color = self.pixel_color(row, col, good_to_trace_ref);
-                vec![color.r, color.g, color.b] // Return the pixel color as a flat vector
+        let radiance = pixels.iter().flat_map(|(color, _)| color.to_array()).collect();
+        // the passes are built from the hits found for the colors, without any more rays
+        let passes = camera
+            .passes
+            .iter()
+            .map(|pass| {
+                pixels
+                    .par_iter()
+                    .flat_map_iter(|(_, hit)| pass.values(hit.as_ref()))
+                    .collect()
             })
-            .flatten() // Flatten the results into a single vector
-            .collect() // Collect into a Vec<u8> and return
+            .collect();
+
+        Frame { radiance, passes }
     }
 }